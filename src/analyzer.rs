@@ -33,6 +33,11 @@ pub fn analyze_url(command_line_url: &str) -> BlobResult<DownloadOption> {
 
 /// Given a youtube url determines whether it refers to a video/playlist
 fn inspect_yt_url(yt_url: Url) -> BlobResult<DownloadOption> {
+    // Channels (and their tabs) are downloaded like playlists of all their videos
+    if is_channel_path(yt_url.path()) {
+        return Ok(DownloadOption::YtPlaylist);
+    }
+
     if let Some(query) = yt_url.query() {
         // Also urls can be part of a playlist but not have an index, just an id
         // example: https://www.youtube.com/watch?v=GNxZ_izoC8I&list=PLl-vhnGPY7cqQ0b_NXy1qyMVsA9LHiPmv
@@ -83,4 +88,104 @@ fn inspect_yt_url(yt_url: Url) -> BlobResult<DownloadOption> {
     }
 
     Err(BlobdlError::QueryCouldNotBeParsed)
-}
\ No newline at end of file
+}
+
+/// `/@Handle`, `/channel/UC...`, `/c/Name` and `/user/Name`, with or without a tab such as `/videos`
+fn is_channel_path(path: &str) -> bool {
+    let mut parts = path.trim_start_matches('/').split('/');
+    match (parts.next(), parts.next()) {
+        (Some(handle), _) if handle.len() > 1 && handle.starts_with('@') => true,
+        (Some("channel" | "c" | "user"), Some(name)) => !name.is_empty(),
+        _ => false,
+    }
+}
+
+/// Returns the moment of the video the url links to, in seconds
+///
+/// Youtube uses `t=` (`t=90`, `t=90s`, `t=1m30s`, `t=1h2m3s`) while embedded players use `start=`
+pub fn start_time(command_line_url: &str) -> Option<u64> {
+    let url = Url::parse(command_line_url).ok()?;
+
+    let (_, value) = url.query_pairs()
+        .find(|(key, _)| key == "t" || key == "start")?;
+
+    parse_youtube_time(&value)
+}
+
+/// Parses the time format used by youtube links: plain seconds or a mix of hours, minutes and seconds (1h2m3s)
+fn parse_youtube_time(time: &str) -> Option<u64> {
+    if time.is_empty() {
+        return None;
+    }
+    if let Ok(seconds) = time.parse() {
+        return Some(seconds);
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+
+    for c in time.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+        } else {
+            let multiplier = match c {
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return None,
+            };
+            total += number.parse::<u64>().ok()? * multiplier;
+            number.clear();
+        }
+    }
+
+    // Numbers have to be followed by a unit
+    if number.is_empty() {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn youtube_times() {
+        assert_eq!(parse_youtube_time("90"), Some(90));
+        assert_eq!(parse_youtube_time("90s"), Some(90));
+        assert_eq!(parse_youtube_time("1m30s"), Some(90));
+        assert_eq!(parse_youtube_time("1h2m3s"), Some(3723));
+        assert_eq!(parse_youtube_time("2h"), Some(7200));
+        assert_eq!(parse_youtube_time("1h30"), None);
+        assert_eq!(parse_youtube_time("1x"), None);
+        assert_eq!(parse_youtube_time("-5"), None);
+        assert_eq!(parse_youtube_time(""), None);
+    }
+
+    #[test]
+    fn channels() {
+        for url in ["https://www.youtube.com/@Handle",
+                    "https://www.youtube.com/@Handle/videos",
+                    "https://www.youtube.com/channel/UCuAXFkgsw1L7xaCfnd5JJOw",
+                    "https://www.youtube.com/c/Name/streams",
+                    "https://youtube.com/user/Name"] {
+            assert_eq!(analyze_url(url).ok(), Some(DownloadOption::YtPlaylist), "{}", url);
+        }
+        assert!(!is_channel_path("/@"));
+        assert!(!is_channel_path("/channel/"));
+        assert!(!is_channel_path("/watch"));
+        assert_eq!(analyze_url("https://www.youtube.com/watch?v=dQw4w9WgXcQ").ok(), Some(DownloadOption::YtVideo(1)));
+    }
+
+    #[test]
+    fn start_times() {
+        assert_eq!(start_time("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1h2m3s"), Some(3723));
+        assert_eq!(start_time("https://youtu.be/dQw4w9WgXcQ?t=42"), Some(42));
+        assert_eq!(start_time("https://www.youtube.com/embed/dQw4w9WgXcQ?start=75"), Some(75));
+        assert_eq!(start_time("https://www.youtube.com/watch?v=dQw4w9WgXcQ"), None);
+        assert_eq!(start_time("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=soon"), None);
+        assert_eq!(start_time("not a url"), None);
+    }
+}
@@ -1,21 +1,76 @@
+use std::process::ExitCode;
 use colored::Colorize;
-use crate::{parser, ui_prompts};
+use crate::{events, parser, ui_prompts, ytdlp};
 use crate::dispatcher::dispatch;
-use which::which;
+
+/// How blob-dl ended, so that scripts can tell what happened without reading its output.
+///
+/// Invalid command line arguments are reported by clap, which also exits with UsageError (2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Everything was downloaded, or there was nothing new to download
+    Success = 0,
+    /// Nothing could be downloaded, or blob-dl ran into an error
+    Failure = 1,
+    /// The url, the command line or the config file are wrong
+    UsageError = 2,
+    /// Some videos were downloaded but others failed
+    PartialFailure = 3,
+    /// yt-dlp isn't installed, or ffmpeg isn't and the files couldn't be processed without it
+    MissingDependency = 4,
+    /// The user stopped blob-dl with Ctrl-C, shells report a process killed by SIGINT the same way
+    Interrupted = 130,
+}
+
+impl ExitStatus {
+    /// `succeeded` counts the videos which were downloaded (or already had been), `failed` the ones which weren't
+    pub(crate) fn from_counts(succeeded: usize, failed: usize) -> ExitStatus {
+        match (succeeded, failed) {
+            (_, 0) => ExitStatus::Success,
+            (0, _) => ExitStatus::Failure,
+            _ => ExitStatus::PartialFailure,
+        }
+    }
+
+    /// The status of commands which download more than one thing (such as `queue run`) when two of them ended differently.
+    ///
+    /// It is only a partial failure if something succeeded, otherwise it is the worst of the two failures
+    pub(crate) fn combine(self, other: ExitStatus) -> ExitStatus {
+        match (self, other) {
+            (ExitStatus::Interrupted, _) | (_, ExitStatus::Interrupted) => ExitStatus::Interrupted,
+            (ExitStatus::MissingDependency, _) | (_, ExitStatus::MissingDependency) => ExitStatus::MissingDependency,
+            (first, second) if first == second => first,
+            (ExitStatus::Success | ExitStatus::PartialFailure, _) | (_, ExitStatus::Success | ExitStatus::PartialFailure) => ExitStatus::PartialFailure,
+            // Failure and UsageError
+            _ => ExitStatus::UsageError,
+        }
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> ExitCode {
+        ExitCode::from(status as u8)
+    }
+}
 
 /// Handles most of the running logic behind blob-dl
 /// 
 /// First it checks whether yt-dlp is installed
 /// 
 /// Then it launches functions to parse command-line arguments and passes them to dispatcher()
-pub fn run() {
+///
+/// Returns the status blob-dl should exit with
+pub fn run() -> ExitStatus {
     // Processed command line arguments live here
     let config = parser::parse_config();
+    if config.as_ref().is_ok_and(|config| config.output_format() == parser::OutputFormat::Json) {
+        events::enable();
+    }
     #[cfg(debug_assertions)]
     println!("##DEBUG## {:?}", config);
 
     // tested with yt-dlp 2025.03.31
-    if which("yt-dlp").is_ok() {
+    if ytdlp::is_installed() {
         // check whether yt-dlp's version is compatible with this version of blob-dl
         match parser::is_ytdlp_compatible() {
             Ok(false) => {
@@ -33,17 +88,54 @@ pub fn run() {
         match config {
             Ok(config) => {
                 // Ask for more input > Generate a command > Execute yt-dlp
-                if let Err(err) = dispatch(&config) {
-                    // Tell the user about the error
-                    err.report();
+                match dispatch(&config) {
+                    Ok(status) => status,
+                    Err(err) => {
+                        // Tell the user about the error
+                        err.report();
+                        err.exit_status()
+                    }
                 }
             }
             Err(err) => {
                 err.report();
+                err.exit_status()
             }
         }
     } else {
         // ytdlp is not installed!
         eprintln!("{}", crate::ui_prompts::YTDLP_NOT_INSTALLED);
+        ExitStatus::MissingDependency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ExitStatus::*;
+
+    #[test]
+    fn combined_statuses() {
+        let table = [
+            (Success, Success, Success),
+            (Success, PartialFailure, PartialFailure),
+            (Success, Failure, PartialFailure),
+            (Success, UsageError, PartialFailure),
+            (PartialFailure, PartialFailure, PartialFailure),
+            (PartialFailure, Failure, PartialFailure),
+            (PartialFailure, UsageError, PartialFailure),
+            (Failure, Failure, Failure),
+            (Failure, UsageError, UsageError),
+            (UsageError, UsageError, UsageError),
+            (Success, MissingDependency, MissingDependency),
+            (Failure, MissingDependency, MissingDependency),
+            (MissingDependency, MissingDependency, MissingDependency),
+            (Success, Interrupted, Interrupted),
+            (MissingDependency, Interrupted, Interrupted),
+        ];
+        for (first, second, combined) in table {
+            assert_eq!(first.combine(second), combined, "{:?} + {:?}", first, second);
+            assert_eq!(second.combine(first), combined, "{:?} + {:?}", second, first);
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,174 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+
+use crate::dispatcher;
+use crate::error::BlobResult;
+
+/// Name of the archive kept in an output directory
+pub(crate) const ARCHIVE_FILE_NAME: &str = "blob-dl-archive.txt";
+
+/// Where the archive shared by every download lives
+pub(crate) fn global_archive_path() -> BlobResult<PathBuf> {
+    dispatcher::get_data_dir().map(|dir| dir.join("archive.txt"))
+}
+
+/// yt-dlp's download archive: it contains a line like `youtube dQw4w9WgXcQ` for every video it downloaded
+#[derive(Debug)]
+pub(crate) struct Archive {
+    path: PathBuf,
+    lines: Vec<String>,
+}
+
+impl Archive {
+    /// An archive which doesn't exist yet is empty
+    pub(crate) fn read(path: &Path) -> BlobResult<Archive> {
+        let lines = match fs::read_to_string(path) {
+            Ok(contents) => contents.lines().map(str::to_string).collect(),
+            Err(err) if err.kind() == ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Archive { path: path.to_path_buf(), lines })
+    }
+
+    pub(crate) fn contains(&self, video_id: &str) -> bool {
+        self.lines.iter().any(|line| id_of(line) == Some(video_id))
+    }
+
+    /// Removes the given videos so that they are downloaded again next time, returns how many lines were removed
+    fn remove(&mut self, video_ids: &[String]) -> usize {
+        let before = self.lines.len();
+        self.lines.retain(|line| !id_of(line).is_some_and(|id| video_ids.iter().any(|video_id| video_id == id)));
+        before - self.lines.len()
+    }
+
+    /// Removes duplicated lines and lines yt-dlp wouldn't understand, returns how many lines were removed.
+    ///
+    /// The lines which are kept are rewritten as `extractor id`, without extra whitespace
+    fn prune(&mut self) -> usize {
+        let before = self.lines.len();
+        let mut seen = std::collections::HashSet::new();
+        self.lines = self.lines.iter()
+            .filter(|line| id_of(line).is_some())
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| seen.insert(line.clone()))
+            .collect();
+        before - self.lines.len()
+    }
+
+    fn save(&self) -> BlobResult<()> {
+        let mut contents = self.lines.join("\n");
+        if !contents.is_empty() {
+            contents.push('\n');
+        }
+        fs::write(&self.path, contents)?;
+        Ok(())
+    }
+}
+
+/// The id of the video an archive line refers to, None if the line isn't `extractor id`
+fn id_of(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    let _extractor = words.next()?;
+    let id = words.next()?;
+
+    if words.next().is_none() {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// `blob-dl archive`: shows what an archive contains, removes videos from it or cleans it up.
+///
+/// `location` can be an archive file or the directory containing it, None means the global archive
+pub(crate) fn run_subcommand(location: Option<&PathBuf>, remove: &[String], prune: bool) -> BlobResult<()> {
+    let path = match location {
+        Some(location) if location.is_dir() => location.join(ARCHIVE_FILE_NAME),
+        Some(location) => location.clone(),
+        None => global_archive_path()?,
+    };
+
+    if !path.exists() {
+        println!("{} There is no download archive at {}", "[blob-dl]".purple(), path.display());
+        return Ok(());
+    }
+    let mut archive = Archive::read(&path)?;
+
+    if !remove.is_empty() || prune {
+        let removed = archive.remove(remove);
+        let pruned = if prune { archive.prune() } else { 0 };
+        archive.save()?;
+
+        if !remove.is_empty() {
+            println!("{} Removed {} video(s), they will be downloaded again", "[blob-dl]".purple(), removed);
+        }
+        if prune {
+            println!("{} Removed {} duplicated or invalid line(s)", "[blob-dl]".purple(), pruned);
+        }
+        return Ok(());
+    }
+
+    let videos = archive.lines.iter().filter(|line| id_of(line).is_some()).count();
+    println!("{} {} contains {} video(s)", "[blob-dl]".purple(), path.display(), videos);
+    for line in &archive.lines {
+        match id_of(line) {
+            Some(_) => println!("   {}", line),
+            None => println!("   {} {}", line, "(invalid, remove it with --prune)".yellow()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(lines: &[&str]) -> Archive {
+        Archive { path: PathBuf::from(ARCHIVE_FILE_NAME), lines: lines.iter().map(|line| line.to_string()).collect() }
+    }
+
+    #[test]
+    fn ids() {
+        assert_eq!(id_of("youtube dQw4w9WgXcQ"), Some("dQw4w9WgXcQ"));
+        assert_eq!(id_of("youtube dQw4w9WgXcQ  \t"), Some("dQw4w9WgXcQ"));
+        assert_eq!(id_of("  youtube\tdQw4w9WgXcQ"), Some("dQw4w9WgXcQ"));
+
+        assert_eq!(id_of(""), None);
+        assert_eq!(id_of("   "), None);
+        assert_eq!(id_of("youtube"), None);
+        assert_eq!(id_of("youtube dQw4w9WgXcQ extra"), None);
+    }
+
+    #[test]
+    fn prune_removes_duplicated_and_malformed_lines() {
+        let mut archive = archive(&[
+            "youtube aaa",
+            "youtube bbb ",
+            "not-an-archive-line",
+            "youtube aaa",
+            "",
+            "youtube  bbb",
+            "youtube ccc extra",
+            "soundcloud aaa",
+        ]);
+
+        assert_eq!(archive.prune(), 5);
+        assert_eq!(archive.lines, ["youtube aaa", "youtube bbb", "soundcloud aaa"]);
+        assert!(archive.contains("bbb"));
+        assert!(!archive.contains("ccc"));
+
+        // Pruning twice doesn't change anything
+        assert_eq!(archive.prune(), 0);
+    }
+
+    #[test]
+    fn remove_every_line_of_a_video() {
+        let mut archive = archive(&["youtube aaa", "youtube bbb", "youtube aaa ", "youtube ccc"]);
+
+        assert_eq!(archive.remove(&["aaa".to_string(), "missing".to_string()]), 2);
+        assert_eq!(archive.lines, ["youtube bbb", "youtube ccc"]);
+    }
+}
@@ -11,6 +11,9 @@ use crate::error::BlobResult;
 /// 
 /// Returns the command along with a DownloadConfig object, which contains all the user-specified preferences
 pub(crate) fn generate_command(url: &str, download_option: &analyzer::DownloadOption, user_config: youtube::config::DownloadConfig) -> BlobResult<(std::process::Command, youtube::config::DownloadConfig)> {
+    // The wizard doesn't know about these, they are carried over from the config file
+    let advanced = user_config.advanced.clone();
+
     // Get preferences from the user, various errors may occur
     let unchecked_config = match download_option {
         analyzer::DownloadOption::YtPlaylist => youtube::yt_playlist::assemble_data(url, user_config),
@@ -19,7 +22,8 @@ pub(crate) fn generate_command(url: &str, download_option: &analyzer::DownloadOp
     };
 
     match unchecked_config {
-        Ok(safe) => {
+        Ok(mut safe) => {
+            safe.advanced = advanced;
             // Everything went smoothly, now generate a yt-dlp command
             let (command, local_config) = safe.build_command()?;
             Ok((command, local_config))
@@ -4,11 +4,12 @@ pub mod config;
 
 use crate::error::{BlobdlError, BlobResult};
 use dialoguer::console::Term;
-use dialoguer::{theme::ColorfulTheme, Select, Input};
+use dialoguer::{theme::ColorfulTheme, Select, Input, MultiSelect};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::{env, fmt};
 use colored::Colorize;
+use crate::tracklist;
 
 // Functions used both in yt_video.rs and yt_playlist.rs
 /// Asks the user whether they want to download video files or audio-only
@@ -62,19 +63,146 @@ fn get_output_path(term: &Term) -> BlobResult<String> {
     }
 }
 
+/// Asks whether an audio file should be split into tracks using the timestamps listed in the video's description
+///
+/// Many album uploads have no chapters but a tracklist like `03:41 Song B` in their description
+fn get_tracklist_preference(term: &Term) -> BlobResult<bool> {
+    let tracklist_options = &[
+        "No",
+        "Yes",
+    ];
+
+    let split_selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Do you want to split the audio into tracks using the timestamps in the description? (useful for albums)")
+        .default(0)
+        .items(tracklist_options)
+        .interact_on(term)?;
+
+    match split_selection {
+        0 => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+/// Every category of segment SponsorBlock knows about, along with how it is presented to the user
+const SPONSORBLOCK_CATEGORIES: [(&str, &str); 8] = [
+    ("sponsor",        "Sponsors"),
+    ("intro",          "Intros/intermissions"),
+    ("outro",          "Outros/endcards"),
+    ("selfpromo",      "Self promotion"),
+    ("preview",        "Previews/recaps"),
+    ("filler",         "Filler tangents"),
+    ("interaction",    "Interaction reminders (subscribe, like, ...)"),
+    ("music_offtopic", "Non-music sections in music videos"),
+];
+
+/// Asks whether SponsorBlock should be used to remove or mark sponsored segments (and others) REQUIRES FFMPEG
+fn get_sponsorblock_preference(term: &Term) -> BlobResult<SponsorBlockPreferences> {
+    let sponsorblock_options = &[
+        "Keep them",
+        "Remove them",
+        "Mark them as chapters",
+    ];
+
+    let action = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("What do you want to do with sponsored segments? (they are found using SponsorBlock)")
+        .default(0)
+        .items(sponsorblock_options)
+        .interact_on(term)?;
+
+    if action == 0 {
+        return Ok(SponsorBlockPreferences::Disabled);
+    }
+
+    let labels: Vec<&str> = SPONSORBLOCK_CATEGORIES.iter().map(|(_, label)| *label).collect();
+    // Only sponsors are selected by default
+    let defaults: Vec<bool> = SPONSORBLOCK_CATEGORIES.iter().map(|(category, _)| *category == "sponsor").collect();
+
+    let selection = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Which kinds of segments? [space bar to select]")
+        .items(&labels)
+        .defaults(&defaults)
+        .interact_on(term)?;
+
+    let categories: Vec<String> = selection.into_iter()
+        .map(|i| SPONSORBLOCK_CATEGORIES[i].0.to_string())
+        .collect();
+
+    if categories.is_empty() {
+        // Nothing was selected, so there is nothing for SponsorBlock to do
+        Ok(SponsorBlockPreferences::Disabled)
+    } else if action == 1 {
+        Ok(SponsorBlockPreferences::Remove(categories))
+    } else {
+        Ok(SponsorBlockPreferences::Mark(categories))
+    }
+}
+
+/// Asks whether the whole video should be downloaded or only some parts of it REQUIRES FFMPEG
+fn get_clip_sections(term: &Term) -> BlobResult<Vec<ClipSection>> {
+    let clip_options = &[
+        "The whole video",
+        "Only some parts of it [specify]",
+    ];
+
+    let clip_selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Which part of the video do you want to download?")
+        .default(0)
+        .items(clip_options)
+        .interact_on(term)?;
+
+    if clip_selection == 0 {
+        return Ok(vec![]);
+    }
+
+    let theme = ColorfulTheme::default();
+    let mut input = Input::<String>::with_theme(&theme);
+    input.with_prompt("Time ranges, separated by commas (e.g. 1:00-3:00, 1:02:00-1:04:30, 5:00-end)")
+        .validate_with(|ranges: &String| -> Result<(), &str> {
+            match ClipSection::parse_list(ranges) {
+                Some(_) => Ok(()),
+                None => Err("Ranges look like START-END, for example 1:30-4:00"),
+            }
+        });
+
+    // The input was validated so parsing can't fail here
+    Ok(ClipSection::parse_list(&input.interact_text_on(term)?).unwrap_or_default())
+}
+
+/// Asks whether yt-dlp should re-encode the video around the cuts so that clips start and end exactly where requested
+fn get_keyframes_preference(term: &Term) -> BlobResult<bool> {
+    let keyframes_options = &[
+        "No, cut at the nearest keyframe (faster)",
+        "Yes, cut exactly where requested (slower, re-encodes around the cuts)",
+    ];
+
+    let keyframes_selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Do the clips need to be precise?")
+        .default(0)
+        .items(keyframes_options)
+        .interact_on(term)?;
+
+    match keyframes_selection {
+        0 => Ok(false),
+        _ => Ok(true),
+    }
+}
+
 
 use spinoff;
 use std::process;
+use crate::events;
+use crate::ytdlp;
 // Running yt-dlp -j <...>
 use execute::Execute;
 use crate::error::BlobdlError::JsonGenerationError;
 
 /// Returns the output of <yt-dlp -J url>: a JSON dump of all the available format information for a video
 fn get_ytdlp_formats(url: &str) -> Result<process::Output, std::io::Error> {
-    // Neat animation to entertain the user while the information is being downloaded
-    let mut sp = spinoff::Spinner::new(spinoff::spinners::Dots10, "Fetching available formats...", spinoff::Color::Cyan);
+    // Neat animation to entertain the user while the information is being downloaded, not with --output-format json
+    let sp = (!events::enabled()).then(|| spinoff::Spinner::new(spinoff::spinners::Dots10, "Fetching available formats...", spinoff::Color::Cyan));
 
-    let mut command = process::Command::new("yt-dlp");
+    let mut command = ytdlp::command();
     // Get a JSON dump of all the available formats related to this url
     command.arg("-J");
     // Continue even if you get errors
@@ -88,11 +216,87 @@ fn get_ytdlp_formats(url: &str) -> Result<process::Output, std::io::Error> {
     let output = command.execute_output();
 
     // Stop the ui spinner
-    sp.success("Formats downloaded successfully".bold().to_string().as_str());
+    if let Some(mut sp) = sp {
+        sp.success("Formats downloaded successfully".bold().to_string().as_str());
+    }
 
     output
 }
 
+/// An entry of a playlist, as listed by yt-dlp --flat-playlist
+#[derive(Debug, Clone)]
+pub(crate) struct PlaylistItem {
+    pub(crate) id: String,
+    pub(crate) title: Option<String>,
+    /// Starts from 1
+    pub(crate) playlist_index: usize,
+}
+
+/// Lists the entries of a playlist without downloading anything.
+///
+/// If `whole_playlist` is false and the url refers to a video in a playlist, only that video is listed
+pub(crate) fn get_playlist_items(url: &str, whole_playlist: bool) -> BlobResult<Vec<PlaylistItem>> {
+    let sp = (!events::enabled()).then(|| spinoff::Spinner::new(spinoff::spinners::Dots10, "Fetching the playlist's entries...", spinoff::Color::Cyan));
+
+    let mut command = ytdlp::command();
+    // Don't extract information about each video, the playlist's page is enough
+    command.arg("--flat-playlist");
+    // Continue even if you get errors
+    command.arg("-i");
+    command.arg(if whole_playlist { "--yes-playlist" } else { "--no-playlist" });
+    command.arg("--print").arg("%(.{id,title,playlist_index})j");
+    command.arg(url);
+
+    command.stdout(process::Stdio::piped());
+    command.stderr(process::Stdio::piped());
+    let output = command.execute_output();
+
+    let items = match &output {
+        Ok(output) => parse_playlist_items(&output.stdout),
+        Err(_) => Ok(vec![]),
+    };
+
+    if let Some(mut sp) = sp {
+        // yt-dlp couldn't be started, or it failed without listing anything
+        let failed = match (&output, &items) {
+            (Ok(output), Ok(items)) => !output.status.success() && items.is_empty(),
+            _ => true,
+        };
+        if failed {
+            sp.fail("The playlist's entries couldn't be fetched".bold().to_string().as_str());
+        } else {
+            sp.success("Playlist entries fetched successfully".bold().to_string().as_str());
+        }
+    }
+
+    output?;
+    items
+}
+
+/// Parses what `yt-dlp --flat-playlist` printed, one JSON object per entry
+fn parse_playlist_items(stdout: &[u8]) -> BlobResult<Vec<PlaylistItem>> {
+    #[derive(Deserialize)]
+    struct RawItem {
+        id: String,
+        title: Option<String>,
+        playlist_index: Option<usize>,
+    }
+
+    let items = std::str::from_utf8(stdout)?
+        .lines()
+        .filter_map(|line| serde_json::from_str::<RawItem>(line).ok())
+        .enumerate()
+        .map(|(i, item)| PlaylistItem {
+            id: item.id,
+            title: item.title,
+            // Entries are listed in order, so the position in the list can be used if yt-dlp didn't provide it
+            playlist_index: item.playlist_index.unwrap_or(i + 1),
+        })
+        .collect();
+
+    Ok(items)
+}
+
 /// Ask the user what format they want the downloaded file to be recoded to (yt-dlp postprocessor) REQUIRES FFMPEG
 fn convert_to_format(term: &Term, media_selected: &MediaSelection)
                      -> BlobResult<VideoQualityAndFormatPreferences>
@@ -117,11 +321,6 @@ fn convert_to_format(term: &Term, media_selected: &MediaSelection)
     Ok(VideoQualityAndFormatPreferences::ConvertTo(format_options[user_selection].to_string()))
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-struct Format {
-    format_id: String,
-}
-
 /// Serializes the information about all the formats available for 1 video
 fn serialize_formats(json_dump: Option<&str>) -> BlobResult<VideoSpecs> {
     if let Some(json) = json_dump {
@@ -185,6 +384,8 @@ struct VideoFormat {
     audio_channels: Option<u64>,
     // Video resolution, is "audio only" for audio-only formats
     resolution: String,
+    // Measured in pixels, is null for audio-only formats
+    height: Option<u32>,
     // Measured in MB. Unavailable on sb* formats
     filesize: Option<u64>,
     // Video codec, can be "none"
@@ -216,6 +417,180 @@ pub(crate) enum VideoQualityAndFormatPreferences {
     SmallestSize,
 }
 
+/// A format to fall back to when the one the user chose (a UniqueFormat) isn't available for a video.
+///
+/// yt-dlp tries the chosen format first and then every fallback in order, see DownloadConfig::format_fallback
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) enum FormatFallback {
+    /// Another format id
+    Format(String),
+    /// The best format as tall as `height` (ignored for audio) whose codec starts with `codec` (avc1, vp09, av01, mp4a, opus, ...)
+    SameResolutionAndCodec { height: Option<u32>, codec: String },
+    /// The best format at most this many pixels tall, skipped for audio
+    AtMostHeight(u32),
+    /// The best format available
+    Best,
+}
+
+impl FormatFallback {
+    /// The fallbacks used when the user picks `format` from yt-dlp's list:
+    /// the same resolution and codec, then the best format which isn't taller, then the best format
+    fn chain_for(format: &VideoFormat, media_selected: &MediaSelection) -> Vec<FormatFallback> {
+        let codec = match media_selected {
+            MediaSelection::AudioOnly => format.acodec.as_deref(),
+            _ => Some(format.vcodec.as_str()),
+        };
+        let height = match media_selected {
+            MediaSelection::AudioOnly => None,
+            _ => format.height,
+        };
+
+        let mut chain = vec![];
+        if let Some(codec) = codec.map(codec_family).filter(|codec| !codec.is_empty() && codec != "none") {
+            chain.push(FormatFallback::SameResolutionAndCodec { height, codec });
+        }
+        if let Some(height) = height {
+            chain.push(FormatFallback::AtMostHeight(height));
+        }
+        chain.push(FormatFallback::Best);
+        chain
+    }
+
+    /// yt-dlp's format selector for this fallback, None if it doesn't apply to `media_selected`
+    pub(crate) fn selector(&self, media_selected: &MediaSelection) -> Option<String> {
+        let filtered = |filter: String| match media_selected {
+            // Merging separate video and audio streams needs ffmpeg, formats which have both are the alternative
+            MediaSelection::FullVideo => format!("bv*{}+ba/b{}", filter, filter),
+            MediaSelection::VideoOnly => format!("bv{}", filter),
+            MediaSelection::AudioOnly => format!("ba{}", filter),
+        };
+
+        match self {
+            FormatFallback::Format(id) => Some(id.clone()),
+            FormatFallback::SameResolutionAndCodec { height, codec } => {
+                let (height, codec_field) = match media_selected {
+                    MediaSelection::AudioOnly => (None, "acodec"),
+                    _ => (*height, "vcodec"),
+                };
+                let height = height.map(|height| format!("[height={}]", height)).unwrap_or_default();
+                Some(filtered(format!("{}[{}^={}]", height, codec_field, codec)))
+            }
+            FormatFallback::AtMostHeight(_) if *media_selected == MediaSelection::AudioOnly => None,
+            FormatFallback::AtMostHeight(height) => Some(filtered(format!("[height<={}]", height))),
+            FormatFallback::Best => Some(filtered(String::new())),
+        }
+    }
+
+    /// Whether yt-dlp could have picked a format with these properties because of this fallback.
+    ///
+    /// `format_id` is what yt-dlp downloaded, such as `137+140` when video and audio were merged
+    pub(crate) fn matches(&self, media_selected: &MediaSelection, format_id: &str, height: Option<u32>, vcodec: Option<&str>, acodec: Option<&str>) -> bool {
+        match self {
+            FormatFallback::Format(id) => format_id.split('+').any(|part| part == id),
+            FormatFallback::SameResolutionAndCodec { height: wanted_height, codec } => {
+                let (downloaded_codec, wanted_height) = match media_selected {
+                    MediaSelection::AudioOnly => (acodec, None),
+                    _ => (vcodec, *wanted_height),
+                };
+                wanted_height.is_none_or(|wanted| height == Some(wanted))
+                    && downloaded_codec.is_some_and(|downloaded| codec_family(downloaded) == *codec)
+            }
+            FormatFallback::AtMostHeight(max) => *media_selected != MediaSelection::AudioOnly && height.is_some_and(|height| height <= *max),
+            FormatFallback::Best => true,
+        }
+    }
+}
+
+impl fmt::Display for FormatFallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatFallback::Format(id) => write!(f, "format {}", id),
+            FormatFallback::SameResolutionAndCodec { height: Some(height), codec } => write!(f, "{}p {}", height, codec),
+            FormatFallback::SameResolutionAndCodec { height: None, codec } => write!(f, "best {}", codec),
+            FormatFallback::AtMostHeight(height) => write!(f, "best up to {}p", height),
+            FormatFallback::Best => write!(f, "best available"),
+        }
+    }
+}
+
+/// `avc1.640028` and `avc1.4d401f` are both avc1, yt-dlp's `^=` filter compares codecs the same way
+fn codec_family(codec: &str) -> String {
+    codec.split('.').next().unwrap_or_default().to_string()
+}
+
+/// What to do with the segments SponsorBlock knows about (sponsors, intros, ...)
+///
+/// The Strings are SponsorBlock's category names (sponsor, intro, outro, selfpromo, ...)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) enum SponsorBlockPreferences {
+    Disabled,
+    // Cut the segments out of the downloaded file (post-processor)
+    Remove(Vec<String>),
+    // Add the segments to the file as chapters (post-processor)
+    Mark(Vec<String>),
+}
+
+/// A part of a video which has to be downloaded on its own (yt-dlp's --download-sections)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ClipSection {
+    /// Seconds from the beginning of the video
+    pub(crate) start: u64,
+    /// None means "until the end of the video"
+    pub(crate) end: Option<u64>,
+}
+
+impl ClipSection {
+    /// Parses a range written like `1:00-3:00`, `90-210` or `1:02:00-end`.
+    ///
+    /// A missing start means the beginning of the video, a missing end means the end of the video
+    pub(crate) fn parse(range: &str) -> Option<ClipSection> {
+        let (start, end) = range.trim().split_once('-')?;
+
+        let start = match start.trim() {
+            "" => 0,
+            start => parse_seconds(start)?,
+        };
+        let end = match end.trim() {
+            "" | "end" | "inf" => None,
+            end => Some(parse_seconds(end)?),
+        };
+
+        if let Some(end) = end {
+            if end <= start {
+                return None;
+            }
+        }
+        Some(ClipSection { start, end })
+    }
+
+    /// Parses a comma-separated list of ranges, returns None if any of them is invalid or if there are none
+    pub(crate) fn parse_list(ranges: &str) -> Option<Vec<ClipSection>> {
+        let sections = ranges.split(',')
+            .filter(|range| !range.trim().is_empty())
+            .map(ClipSection::parse)
+            .collect::<Option<Vec<ClipSection>>>()?;
+
+        if sections.is_empty() {
+            None
+        } else {
+            Some(sections)
+        }
+    }
+
+    /// The syntax used by yt-dlp's --download-sections
+    pub(crate) fn to_ytdlp_section(&self) -> String {
+        match self.end {
+            Some(end) => format!("*{}-{}", self.start, end),
+            None => format!("*{}-inf", self.start),
+        }
+    }
+}
+
+/// Timestamps (`1:30`) or plain seconds (`90`)
+fn parse_seconds(time: &str) -> Option<u64> {
+    time.parse().ok().or_else(|| tracklist::parse_timestamp(time))
+}
+
 impl fmt::Display for VideoFormat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut result;
@@ -272,3 +647,101 @@ impl VideoSpecs {
         &self.formats
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(start: u64, end: Option<u64>) -> ClipSection {
+        ClipSection { start, end }
+    }
+
+    #[test]
+    fn clip_sections() {
+        assert_eq!(ClipSection::parse("1:00-3:00"), Some(section(60, Some(180))));
+        assert_eq!(ClipSection::parse(" 90 - 210 "), Some(section(90, Some(210))));
+        assert_eq!(ClipSection::parse("1:02:00-end"), Some(section(3720, None)));
+        assert_eq!(ClipSection::parse("5:00-inf"), Some(section(300, None)));
+        assert_eq!(ClipSection::parse("-2:00"), Some(section(0, Some(120))));
+        assert_eq!(ClipSection::parse("3:00-"), Some(section(180, None)));
+        // The end has to come after the start
+        assert_eq!(ClipSection::parse("3:00-1:00"), None);
+        assert_eq!(ClipSection::parse("1:00-1:00"), None);
+        assert_eq!(ClipSection::parse("1:00"), None);
+        assert_eq!(ClipSection::parse("a-b"), None);
+        assert_eq!(ClipSection::parse("1:60-2:00"), None);
+    }
+
+    #[test]
+    fn clip_section_lists() {
+        assert_eq!(ClipSection::parse_list("1:00-3:00, 5:00-end"), Some(vec![section(60, Some(180)), section(300, None)]));
+        assert_eq!(ClipSection::parse_list("1:00-3:00,"), Some(vec![section(60, Some(180))]));
+        assert_eq!(ClipSection::parse_list("1:00-3:00, 4:00-2:00"), None);
+        assert_eq!(ClipSection::parse_list(" , "), None);
+        assert_eq!(ClipSection::parse_list(""), None);
+    }
+
+    #[test]
+    fn fallback_selectors() {
+        let same = FormatFallback::SameResolutionAndCodec { height: Some(1080), codec: "avc1".to_string() };
+        assert_eq!(same.selector(&MediaSelection::FullVideo).as_deref(), Some("bv*[height=1080][vcodec^=avc1]+ba/b[height=1080][vcodec^=avc1]"));
+        assert_eq!(same.selector(&MediaSelection::VideoOnly).as_deref(), Some("bv[height=1080][vcodec^=avc1]"));
+        let audio = FormatFallback::SameResolutionAndCodec { height: None, codec: "opus".to_string() };
+        assert_eq!(audio.selector(&MediaSelection::AudioOnly).as_deref(), Some("ba[acodec^=opus]"));
+
+        assert_eq!(FormatFallback::Format("22".to_string()).selector(&MediaSelection::FullVideo).as_deref(), Some("22"));
+        assert_eq!(FormatFallback::AtMostHeight(720).selector(&MediaSelection::FullVideo).as_deref(), Some("bv*[height<=720]+ba/b[height<=720]"));
+        assert_eq!(FormatFallback::AtMostHeight(720).selector(&MediaSelection::AudioOnly), None);
+        assert_eq!(FormatFallback::Best.selector(&MediaSelection::FullVideo).as_deref(), Some("bv*+ba/b"));
+        assert_eq!(FormatFallback::Best.selector(&MediaSelection::AudioOnly).as_deref(), Some("ba"));
+    }
+
+    #[test]
+    fn fallback_matches() {
+        let full = MediaSelection::FullVideo;
+        assert!(FormatFallback::Format("137".to_string()).matches(&full, "137+140", Some(1080), Some("avc1"), Some("mp4a")));
+        assert!(!FormatFallback::Format("37".to_string()).matches(&full, "137+140", Some(1080), Some("avc1"), Some("mp4a")));
+
+        let same = FormatFallback::SameResolutionAndCodec { height: Some(1080), codec: "avc1".to_string() };
+        assert!(same.matches(&full, "299+140", Some(1080), Some("avc1.64002a"), Some("mp4a.40.2")));
+        assert!(!same.matches(&full, "298+140", Some(720), Some("avc1.4d401f"), Some("mp4a.40.2")));
+        assert!(!same.matches(&full, "248+251", Some(1080), Some("vp09.00.40.08"), Some("opus")));
+        let audio = FormatFallback::SameResolutionAndCodec { height: None, codec: "opus".to_string() };
+        assert!(audio.matches(&MediaSelection::AudioOnly, "251", None, Some("none"), Some("opus")));
+
+        assert!(FormatFallback::AtMostHeight(720).matches(&full, "18", Some(360), Some("avc1"), Some("mp4a")));
+        assert!(!FormatFallback::AtMostHeight(720).matches(&full, "137+140", Some(1080), Some("avc1"), Some("mp4a")));
+        assert!(!FormatFallback::AtMostHeight(720).matches(&MediaSelection::AudioOnly, "140", None, Some("none"), Some("mp4a")));
+    }
+
+    #[test]
+    fn fallback_chains() {
+        let format = VideoFormat {
+            format_id: "137".to_string(),
+            ext: "mp4".to_string(),
+            fps: Some(30.0),
+            audio_channels: None,
+            resolution: "1920x1080".to_string(),
+            height: Some(1080),
+            filesize: None,
+            vcodec: "avc1.640028".to_string(),
+            acodec: Some("none".to_string()),
+            container: None,
+            tbr: Some(4000.0),
+            filesize_approx: None,
+        };
+        assert_eq!(FormatFallback::chain_for(&format, &MediaSelection::FullVideo), vec![
+            FormatFallback::SameResolutionAndCodec { height: Some(1080), codec: "avc1".to_string() },
+            FormatFallback::AtMostHeight(1080),
+            FormatFallback::Best,
+        ]);
+        // Video-only formats have no audio codec to stick to
+        assert_eq!(FormatFallback::chain_for(&format, &MediaSelection::AudioOnly), vec![FormatFallback::Best]);
+    }
+
+    #[test]
+    fn ytdlp_sections() {
+        assert_eq!(section(60, Some(180)).to_ytdlp_section(), "*60-180");
+        assert_eq!(section(300, None).to_ytdlp_section(), "*300-inf");
+    }
+}
@@ -3,7 +3,12 @@ use crate::analyzer;
 use std::process;
 use serde::{Deserialize, Serialize};
 use crate::analyzer::DownloadOption;
-use crate::error::{BlobResult, BlobdlError};
+use crate::error::{BlobResult, BlobdlError, ErrorCategory, ALL_CATEGORIES};
+use crate::classifier::ErrorRule;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use crate::archive;
+use crate::ytdlp;
 
 /// Contains all the information needed to download a youtube video or playlist
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,10 +20,157 @@ pub(crate) struct DownloadConfig {
     pub(crate) include_indexes: Option<bool>,
     /// The quality and format the user wants the downloaded files to be in
     pub(crate) chosen_format: Option<youtube::VideoQualityAndFormatPreferences>,
+    /// What to download when a UniqueFormat isn't available for a video, tried in order.
+    /// None falls back to the best format, an empty Vec doesn't fall back at all
+    pub(crate) format_fallback: Option<Vec<youtube::FormatFallback>>,
     /// Whether the downloaded files have to be audio-only/video-only/normal video
     pub(crate) media_selected: Option<youtube::MediaSelection>,
     /// Whether the link refers to a p laylist or a single video
     pub(crate) download_target: Option<analyzer::DownloadOption>,
+    /// Whether audio files should be split into tracks using the timestamps listed in the video's description
+    pub(crate) split_by_tracklist: Option<bool>,
+    /// Which SponsorBlock segments to remove from/mark in the downloaded files
+    pub(crate) sponsorblock: Option<youtube::SponsorBlockPreferences>,
+    /// Only download these parts of the video(s), an empty Vec means the whole video
+    pub(crate) sections: Option<Vec<youtube::ClipSection>>,
+    /// Whether the video should be re-encoded around the cuts so that clips are precise
+    pub(crate) force_keyframes_at_cuts: Option<bool>,
+    /// Preferences which the wizard doesn't ask about, in config files they sit next to the other fields
+    #[serde(flatten)]
+    pub(crate) advanced: AdvancedPreferences,
+}
+
+/// Preferences which can only be set with a config file or with command line arguments.
+///
+/// Leaving them out (or setting them to null) means using the default behaviour
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct AdvancedPreferences {
+    /// How many videos of a playlist are downloaded at the same time (each by its own yt-dlp process)
+    pub(crate) parallel_downloads: Option<usize>,
+    /// Retry failed videos automatically instead of asking the user which ones to re-download
+    pub(crate) retry: Option<RetryPolicy>,
+    /// Keep track of downloaded videos so that they are skipped next time
+    pub(crate) download_archive: Option<ArchivePreference>,
+    /// Stop yt-dlp when a video takes too long or stops making progress
+    pub(crate) supervision: Option<Supervision>,
+    /// The user's own commands, run when a file is downloaded, a video fails or the run is over
+    pub(crate) hooks: Option<Hooks>,
+    /// Patterns which sort errors blob-dl doesn't know (or gets wrong) into an ErrorCategory
+    pub(crate) error_rules: Option<Vec<ErrorRule>>,
+    /// A cookies file (in the Netscape format) which yt-dlp uses to log in, needed for private and age-restricted videos
+    pub(crate) cookies: Option<PathBuf>,
+}
+
+/// Where the download archive (the list of videos which have already been downloaded) is kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ArchivePreference {
+    /// Every output directory has its own archive
+    PerDirectory,
+    /// A single archive in blob-dl's data directory, shared by every download
+    Global,
+    /// An archive file chosen by the user
+    Custom(PathBuf),
+}
+
+impl ArchivePreference {
+    /// Parses the value of --download-archive: `dir`, `global` or the path to an archive file
+    pub(crate) fn from_argument(argument: &str) -> ArchivePreference {
+        match argument {
+            "dir" => ArchivePreference::PerDirectory,
+            "global" => ArchivePreference::Global,
+            path => ArchivePreference::Custom(PathBuf::from(path)),
+        }
+    }
+}
+
+/// How failed videos are retried when nobody is asked about it.
+///
+/// Fields which are left out of a config file take their default value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct RetryPolicy {
+    /// How many times a video is downloaded at most, the first attempt included
+    pub(crate) max_attempts: u32,
+    /// Seconds to wait before the first round of retries, the delay doubles every round
+    pub(crate) initial_delay: f64,
+    /// The delay never grows past this many seconds
+    pub(crate) max_delay: f64,
+    /// Which kinds of errors are worth retrying
+    pub(crate) retry_on: Vec<ErrorCategory>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_delay: 5.0,
+            max_delay: 120.0,
+            retry_on: ALL_CATEGORIES.into_iter().filter(ErrorCategory::retry_by_default).collect(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn retries(&self, category: ErrorCategory) -> bool {
+        self.retry_on.contains(&category)
+    }
+
+    /// How long to wait before the `round`th round of retries (starting from 1).
+    ///
+    /// The exponential delay is randomly shortened by up to half, so that parallel downloads
+    /// which failed together don't all hit youtube again at the same moment
+    pub(crate) fn delay(&self, round: u32) -> Duration {
+        let exponential = self.initial_delay.max(0.0) * 2f64.powi(round.saturating_sub(1).min(30) as i32);
+        let capped = exponential.min(self.max_delay.max(0.0));
+
+        // There is no need for a proper random number generator here
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+        let jitter = (nanos % 1000) as f64 / 1000.0;
+
+        Duration::from_secs_f64(capped * (1.0 - jitter / 2.0))
+    }
+}
+
+/// Limits after which blob-dl stops yt-dlp, the video counts as failed and is retried like any other
+/// failed video (see ErrorCategory::TimedOut). None means no limit
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Supervision {
+    /// Seconds a video can take to download, postprocessing included
+    pub(crate) timeout: Option<f64>,
+    /// Seconds yt-dlp can go without printing anything while downloading
+    pub(crate) stall_timeout: Option<f64>,
+}
+
+/// Shell commands run by blob-dl, they get information about what happened through environment variables (see run/hooks.rs).
+///
+/// A hook which fails is reported, but it doesn't stop the download
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Hooks {
+    /// Run for every file as soon as it is downloaded
+    pub(crate) on_file_complete: Option<String>,
+    /// Run once at the end of every run
+    pub(crate) on_run_complete: Option<String>,
+    /// Run at the end of a run for every video which couldn't be downloaded
+    pub(crate) on_error: Option<String>,
+}
+
+impl Supervision {
+    /// Why a process which started at `started` and last made progress at `last_progress` should be stopped, if it should.
+    ///
+    /// Postprocessing can go on for a long time without any output, so stalls are only detected while downloading
+    pub(crate) fn check(&self, started: Instant, last_progress: Instant, postprocessing: bool) -> Option<String> {
+        if let Some(timeout) = self.timeout.filter(|timeout| started.elapsed().as_secs_f64() > *timeout) {
+            return Some(format!("it took more than {}s", timeout));
+        }
+        match self.stall_timeout {
+            Some(stall_timeout) if !postprocessing && last_progress.elapsed().as_secs_f64() > stall_timeout => {
+                Some(format!("it made no progress for {}s", stall_timeout))
+            }
+            _ => None,
+        }
+    }
 }
 
 impl DownloadConfig {
@@ -29,17 +181,29 @@ impl DownloadConfig {
             output_path: None,
             include_indexes: None,
             chosen_format: None,
+            format_fallback: None,
             media_selected: None,
             download_target: None,
+            split_by_tracklist: None,
+            sponsorblock: None,
+            sections: None,
+            force_keyframes_at_cuts: None,
+            advanced: AdvancedPreferences::default(),
         }
     }
     
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new_playlist (
         url: &str,
         output_path: String,
         include_indexes: bool,
         chosen_format: youtube::VideoQualityAndFormatPreferences,
+        format_fallback: Option<Vec<youtube::FormatFallback>>,
         media_selected: youtube::MediaSelection,
+        split_by_tracklist: bool,
+        sponsorblock: youtube::SponsorBlockPreferences,
+        sections: Vec<youtube::ClipSection>,
+        force_keyframes_at_cuts: bool,
     )
         -> DownloadConfig
     {
@@ -48,26 +212,44 @@ impl DownloadConfig {
             output_path: Some(output_path), 
             include_indexes: Some(include_indexes), 
             chosen_format: Some(chosen_format), 
+            format_fallback,
             media_selected: Some(media_selected),
-            download_target: Some(analyzer::DownloadOption::YtPlaylist) }
+            download_target: Some(analyzer::DownloadOption::YtPlaylist),
+            split_by_tracklist: Some(split_by_tracklist),
+            sponsorblock: Some(sponsorblock),
+            sections: Some(sections),
+            force_keyframes_at_cuts: Some(force_keyframes_at_cuts),
+            advanced: AdvancedPreferences::default() }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new_video (
         url: &str,
         chosen_format: youtube::VideoQualityAndFormatPreferences,
+        format_fallback: Option<Vec<youtube::FormatFallback>>,
         output_path: String,
         media_selected: youtube::MediaSelection,
         playlist_index: usize,
+        split_by_tracklist: bool,
+        sponsorblock: youtube::SponsorBlockPreferences,
+        sections: Vec<youtube::ClipSection>,
+        force_keyframes_at_cuts: bool,
     )
         -> DownloadConfig
     {
         DownloadConfig { 
             url: Some(url.to_string()), 
             chosen_format: Some(chosen_format), 
+            format_fallback,
             output_path: Some(output_path), 
             media_selected: Some(media_selected),
             include_indexes: Some(false), 
-            download_target: Some(analyzer::DownloadOption::YtVideo(playlist_index)) }
+            download_target: Some(analyzer::DownloadOption::YtVideo(playlist_index)),
+            split_by_tracklist: Some(split_by_tracklist),
+            sponsorblock: Some(sponsorblock),
+            sections: Some(sections),
+            force_keyframes_at_cuts: Some(force_keyframes_at_cuts),
+            advanced: AdvancedPreferences::default() }
     }
 }
 
@@ -93,7 +275,29 @@ impl DownloadConfig {
     }
 
     fn build_yt_playlist_command(&self) -> BlobResult<process::Command>{
-        let mut command = process::Command::new("yt-dlp");
+        self.playlist_command(None, true)
+    }
+
+    /// Downloads a single entry of the playlist (`index` starts from 1) as if the whole playlist was being downloaded,
+    /// so the file ends up in the playlist's directory with the same name it would have had.
+    ///
+    /// When yt-dlp downloads a whole playlist it pads indexes to the number of digits of the last one,
+    /// here they are padded to the number of digits of `playlist_count` to get the same result
+    pub(crate) fn build_command_for_playlist_item(&self, index: usize, playlist_count: usize) -> BlobResult<process::Command> {
+        self.playlist_command(Some((index, playlist_count)), true)
+    }
+
+    /// Like build_command_for_playlist_item(), but the file is saved straight into the output directory
+    /// instead of a directory named after the playlist: sync mirrors a playlist into a directory chosen by the user
+    pub(crate) fn build_command_for_sync(&self, index: usize, playlist_count: usize) -> BlobResult<process::Command> {
+        self.playlist_command(Some((index, playlist_count)), false)
+    }
+
+    /// `item` is the index of the only entry which has to be downloaded and the size of the playlist, None to download everything.
+    ///
+    /// `playlist_directory` is whether files go into a directory named after the playlist
+    fn playlist_command(&self, item: Option<(usize, usize)>, playlist_directory: bool) -> BlobResult<process::Command> {
+        let mut command = ytdlp::command();
 
         // Continue even when errors are encountered
         command.arg("-i");
@@ -101,8 +305,12 @@ impl DownloadConfig {
         // If the url refers to a video in a playlist, download the whole playlist
         command.arg("--yes-playlist");
 
+        if let Some((index, _)) = item {
+            command.arg("--playlist-items").arg(index.to_string());
+        }
+
         // Setup output directory and naming scheme
-        self.choose_output_path(&mut command)?;
+        self.choose_output_path(&mut command, item.map(|(_, playlist_count)| playlist_count.to_string().len()), playlist_directory)?;
 
         // Makes the id live long enough to be used as an arg for command.
         // If it was fetched from the next match arm the temporary &str would not outlive command
@@ -114,6 +322,12 @@ impl DownloadConfig {
         // Quality and format selection
         self.choose_format(&mut command, id.as_str())?;
 
+        self.choose_sections(&mut command);
+
+        self.choose_archive(&mut command)?;
+
+        self.choose_cookies(&mut command);
+
         if let Some(url) = self.url.clone() {
 
             // Add the playlist's url
@@ -126,9 +340,9 @@ impl DownloadConfig {
     }
     
     fn build_yt_video_command(&self) -> BlobResult<process::Command> {
-        let mut command = process::Command::new("yt-dlp");
+        let mut command = ytdlp::command();
 
-        self.choose_output_path(&mut command)?;
+        self.choose_output_path(&mut command, None, true)?;
 
         if let Some(chosen_format) = &self.chosen_format {
 
@@ -141,6 +355,12 @@ impl DownloadConfig {
 
             self.choose_format(&mut command, &id)?;
 
+            self.choose_sections(&mut command);
+
+            self.choose_archive(&mut command)?;
+
+            self.choose_cookies(&mut command);
+
             command.arg("--no-playlist");
             
             if let Some(DownloadOption::YtVideo(index)) = &self.download_target {
@@ -165,11 +385,13 @@ impl DownloadConfig {
 
     /// Downloads a new video while keeping the current preferences.
     ///
-    /// This function is meant to be used to re-download videos which failed because of issues like bad internet
+    /// This function is meant to be used to re-download videos which failed because of issues like bad internet.
+    /// Playlist entries should go through build_command_for_playlist_item() instead, outside of a playlist
+    /// yt-dlp doesn't know the playlist's name and the video's index
     pub fn build_command_for_video(&self, video_id: &str) -> BlobResult<process::Command> {
-        let mut command = process::Command::new("yt-dlp");
+        let mut command = ytdlp::command();
 
-        self.choose_output_path(&mut command)?;
+        self.choose_output_path(&mut command, None, true)?;
 
         if let Some(chosen_format) = &self.chosen_format {
 
@@ -182,6 +404,12 @@ impl DownloadConfig {
 
             self.choose_format(&mut command, id.as_str())?;
 
+            self.choose_sections(&mut command);
+
+            self.choose_archive(&mut command)?;
+
+            self.choose_cookies(&mut command);
+
             command.arg("--no-playlist");
 
             command.arg(video_id);
@@ -193,7 +421,9 @@ impl DownloadConfig {
     }
 
     // funzione un po' schifosa
-    fn choose_output_path(&self, command: &mut process::Command) -> BlobResult<()> {
+    /// `index_width` is how many digits playlist indexes are padded to, None lets yt-dlp decide.
+    /// `playlist_directory` is whether playlist entries go into a directory named after the playlist
+    fn choose_output_path(&self, command: &mut process::Command, index_width: Option<usize>, playlist_directory: bool) -> BlobResult<()> {
         if let Some(output_path) = &self.output_path {
             if let Some(download_target) = &self.download_target {
                 if let Some(include_indexes) = self.include_indexes {
@@ -205,24 +435,39 @@ impl DownloadConfig {
                             path_and_scheme.push_str(output_path);
 
                             if *download_target == analyzer::DownloadOption::YtPlaylist {
-                                // Create a directory named after the playlist
-                                #[cfg(target_os = "windows")]
-                                path_and_scheme.push_str("\\%(playlist)s\\");
+                                if playlist_directory {
+                                    // Create a directory named after the playlist
+                                    #[cfg(target_os = "windows")]
+                                    path_and_scheme.push_str("\\%(playlist)s\\");
 
-                                #[cfg(not(target_os = "windows"))]
-                                path_and_scheme.push_str("/%(playlist)s/");
+                                    #[cfg(not(target_os = "windows"))]
+                                    path_and_scheme.push_str("/%(playlist)s/");
+                                } else {
+                                    #[cfg(target_os = "windows")]
+                                    path_and_scheme.push('\\');
+
+                                    #[cfg(not(target_os = "windows"))]
+                                    path_and_scheme.push('/');
+                                }
 
                                 if include_indexes {
-                                    path_and_scheme.push_str("%(playlist_index)s_");
+                                    match index_width {
+                                        Some(width) => path_and_scheme.push_str(&format!("%(playlist_index)0{}d_", width)),
+                                        None => path_and_scheme.push_str("%(playlist_index)s_"),
+                                    }
                                 };
                                 path_and_scheme.push_str("%(title)s");
+                                path_and_scheme.push_str(self.section_suffix());
                             } else {
                                 // Downloading a yt_video
                                 #[cfg(target_os = "windows")]
-                                path_and_scheme.push_str("\\%(title)s.%(ext)s");
+                                path_and_scheme.push_str("\\%(title)s");
 
                                 #[cfg(not(target_os = "windows"))]
-                                path_and_scheme.push_str("/%(title)s.%(ext)s");
+                                path_and_scheme.push_str("/%(title)s");
+
+                                path_and_scheme.push_str(self.section_suffix());
+                                path_and_scheme.push_str(".%(ext)s");
                             }
 
                             path_and_scheme
@@ -254,7 +499,7 @@ impl DownloadConfig {
                             }
 
                             youtube::VideoQualityAndFormatPreferences::UniqueFormat(_) => {
-                                command.arg("-f").arg(format_id);
+                                command.arg("-f").arg(self.format_selector(format_id, media_selected));
                             }
                             youtube::VideoQualityAndFormatPreferences::ConvertTo(f) => {
                                 command.arg("--recode-video").arg(f.as_str());
@@ -275,7 +520,7 @@ impl DownloadConfig {
                             }
 
                             youtube::VideoQualityAndFormatPreferences::UniqueFormat(_) => {
-                                command.arg("-f").arg(format_id);
+                                command.arg("-f").arg(self.format_selector(format_id, media_selected));
                             }
                             youtube::VideoQualityAndFormatPreferences::ConvertTo(f) => {
                                 command.arg("-x").arg("--audio-format").arg(f.as_str());
@@ -294,7 +539,7 @@ impl DownloadConfig {
                             }
 
                             youtube::VideoQualityAndFormatPreferences::UniqueFormat(_) => {
-                                command.arg("-f").arg(format_id);
+                                command.arg("-f").arg(self.format_selector(format_id, media_selected));
                             }
                             youtube::VideoQualityAndFormatPreferences::ConvertTo(f) => {
                                 command.arg("--recode-video").arg(f.as_str());
@@ -310,6 +555,112 @@ impl DownloadConfig {
         } else {
             return Err(BlobdlError::MediaSelectedNotProvided);
         }
+        self.choose_sponsorblock(command);
+        Ok(())
+    }
+
+    /// `format_id` followed by the fallbacks, yt-dlp downloads the first one which is available
+    fn format_selector(&self, format_id: &str, media_selected: &youtube::MediaSelection) -> String {
+        self.format_chain(format_id).iter()
+            .filter_map(|fallback| fallback.selector(media_selected))
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+
+    /// Every format a UniqueFormat(`format_id`) download can end up in, the chosen one first
+    pub(crate) fn format_chain(&self, format_id: &str) -> Vec<youtube::FormatFallback> {
+        let mut chain = vec![youtube::FormatFallback::Format(format_id.to_string())];
+        match &self.format_fallback {
+            Some(fallbacks) => chain.extend(fallbacks.iter().cloned()),
+            None => chain.push(youtube::FormatFallback::Best),
+        }
+        chain
+    }
+
+    /// Only download the parts of the video(s) the user asked for
+    fn choose_sections(&self, command: &mut process::Command) {
+        if let Some(sections) = &self.sections {
+            for section in sections {
+                command.arg("--download-sections").arg(section.to_ytdlp_section());
+            }
+            if !sections.is_empty() && self.force_keyframes_at_cuts == Some(true) {
+                command.arg("--force-keyframes-at-cuts");
+            }
+        }
+    }
+
+    /// Where yt-dlp keeps track of the videos it already downloaded, None if no archive is used
+    pub(crate) fn archive_path(&self) -> BlobResult<Option<PathBuf>> {
+        match &self.advanced.download_archive {
+            None => Ok(None),
+            Some(ArchivePreference::PerDirectory) => {
+                let output_path = self.output_path.as_ref().ok_or(BlobdlError::OutputPathNotProvided)?;
+                Ok(Some(Path::new(output_path).join(archive::ARCHIVE_FILE_NAME)))
+            }
+            Some(ArchivePreference::Global) => archive::global_archive_path().map(Some),
+            Some(ArchivePreference::Custom(path)) => Ok(Some(path.clone())),
+        }
+    }
+
+    /// Videos which are already in the archive are skipped, the ones that get downloaded are added to it
+    fn choose_archive(&self, command: &mut process::Command) -> BlobResult<()> {
+        if let Some(path) = self.archive_path()? {
+            command.arg("--download-archive").arg(path);
+        }
         Ok(())
     }
+
+    fn choose_cookies(&self, command: &mut process::Command) {
+        if let Some(cookies) = &self.advanced.cookies {
+            command.arg("--cookies").arg(cookies);
+        }
+    }
+
+    /// When only parts of a video are downloaded each of them gets its own file, named after the range it covers (in seconds)
+    fn section_suffix(&self) -> &'static str {
+        match &self.sections {
+            Some(sections) if !sections.is_empty() => " [%(section_start)d-%(section_end)d]",
+            _ => "",
+        }
+    }
+
+    /// Config files written by older versions of blob-dl don't have a SponsorBlock preference, in that case it isn't used
+    fn choose_sponsorblock(&self, command: &mut process::Command) {
+        match &self.sponsorblock {
+            Some(youtube::SponsorBlockPreferences::Remove(categories)) => {
+                command.arg("--sponsorblock-remove").arg(categories.join(","));
+            }
+            Some(youtube::SponsorBlockPreferences::Mark(categories)) => {
+                command.arg("--sponsorblock-mark").arg(categories.join(","));
+            }
+            Some(youtube::SponsorBlockPreferences::Disabled) | None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use youtube::{FormatFallback, MediaSelection};
+
+    #[test]
+    fn format_chains() {
+        let mut download_config = DownloadConfig::empty();
+        // Configs which don't say anything fall back to the best format
+        assert_eq!(download_config.format_chain("137"), vec![FormatFallback::Format("137".to_string()), FormatFallback::Best]);
+        assert_eq!(download_config.format_selector("137", &MediaSelection::FullVideo), "137/bv*+ba/b");
+
+        download_config.format_fallback = Some(vec![]);
+        assert_eq!(download_config.format_chain("137"), vec![FormatFallback::Format("137".to_string())]);
+        assert_eq!(download_config.format_selector("137", &MediaSelection::FullVideo), "137");
+
+        download_config.format_fallback = Some(vec![
+            FormatFallback::SameResolutionAndCodec { height: None, codec: "opus".to_string() },
+            FormatFallback::AtMostHeight(720),
+            FormatFallback::Best,
+        ]);
+        assert_eq!(download_config.format_chain("251").len(), 4);
+        // AtMostHeight doesn't apply to audio
+        assert_eq!(download_config.format_selector("251", &MediaSelection::AudioOnly), "251/ba[acodec^=opus]/ba");
+    }
 }
@@ -13,6 +13,8 @@ use which::which;
 /// - Quality/Format
 /// - Output path
 /// - Index inclusion
+/// - Tracklist splitting (audio-only)
+/// - SponsorBlock segments
 ///
 /// Returns a fully configured YtPlaylistConfig, build_command() can be called
 /// 
@@ -31,25 +33,17 @@ pub(crate) fn assemble_data(url: &str, user_config: youtube::config::DownloadCon
         media_selected = get_media_selection(&term)?;
     }
 
-    let chosen_format;
-    if let Some(format) = user_config.chosen_format {
-        // With config files it is possible to "force" blob-dl to try to use ffmpeg
-        if let VideoQualityAndFormatPreferences::ConvertTo(_) = format {
-            // The user wants their files to be converted to another format
-            if !which("ffmpeg").is_ok() {
-                // The conversion cannot be performed because ffmpeg is not installed
-                chosen_format = format::get_format(&term, url, &media_selected)?;
-            } else {
-                // ffmpeg is installed so what was specified in the config file can be used
-                chosen_format = format;
-            }
-        } else {
-            chosen_format = format;
-        }
-    } else {
+    let (chosen_format, picked_fallback) = match user_config.chosen_format {
+        // With config files it is possible to "force" blob-dl to try to use ffmpeg,
+        // the conversion cannot be performed if ffmpeg is not installed
+        Some(VideoQualityAndFormatPreferences::ConvertTo(_)) if which("ffmpeg").is_err() => format::get_format(&term, url, &media_selected)?,
+        // What was specified in the config file can be used
+        Some(format) => (format, None),
         // Config file didn't say anything about file formats
-        chosen_format = format::get_format(&term, url, &media_selected)?;
-    }
+        None => format::get_format(&term, url, &media_selected)?,
+    };
+    // Fallbacks from the config file win over the ones which suit the format picked from yt-dlp's list
+    let format_fallback = user_config.format_fallback.or(picked_fallback);
 
     let output_path;
     // .trim() trims trailing whitespace at the end of the user-specified path (useful is the user is clumsy)
@@ -66,12 +60,49 @@ pub(crate) fn assemble_data(url: &str, user_config: youtube::config::DownloadCon
         include_indexes = get_index_preference(&term)?;
     }
     
+    let split_by_tracklist;
+    if let Some(split) = user_config.split_by_tracklist {
+        split_by_tracklist = split;
+    } else if media_selected == MediaSelection::AudioOnly && which("ffmpeg").is_ok() {
+        // Splitting is done with ffmpeg and only makes sense for audio files
+        split_by_tracklist = get_tracklist_preference(&term)?;
+    } else {
+        split_by_tracklist = false;
+    }
+    
+    let sponsorblock;
+    if let Some(preference) = user_config.sponsorblock {
+        sponsorblock = preference;
+    } else if which("ffmpeg").is_ok() {
+        sponsorblock = get_sponsorblock_preference(&term)?;
+    } else {
+        // Both removing and marking segments are done by ffmpeg
+        sponsorblock = SponsorBlockPreferences::Disabled;
+    }
+    
+    // Clips can only be chosen from the command line or from a config file when downloading a playlist
+    let sections = user_config.sections.unwrap_or_default();
+
+    let force_keyframes_at_cuts;
+    if let Some(keyframes) = user_config.force_keyframes_at_cuts {
+        force_keyframes_at_cuts = keyframes;
+    } else if !sections.is_empty() {
+        force_keyframes_at_cuts = get_keyframes_preference(&term)?;
+    } else {
+        force_keyframes_at_cuts = false;
+    }
+    
     Ok(config::DownloadConfig::new_playlist(
         url,
         output_path,
         include_indexes,
         chosen_format,
+        format_fallback,
         media_selected,
+        split_by_tracklist,
+        sponsorblock,
+        sections,
+        force_keyframes_at_cuts,
     ))
 }
 
@@ -104,7 +135,7 @@ mod format {
     ///
     /// The chosen format will be applied to the entire playlist
     pub(super) fn get_format(term: &Term, url: &str, media_selected: &MediaSelection)
-                             -> BlobResult<VideoQualityAndFormatPreferences>
+                             -> BlobResult<(VideoQualityAndFormatPreferences, Option<Vec<FormatFallback>>)>
     {
 
         // A list of all the format options that can be picked
@@ -130,9 +161,9 @@ mod format {
                 .items(&format_options)
                 .interact_on(term)?;
             match user_selection {
-                0 => Ok(VideoQualityAndFormatPreferences::BestQuality),
-                1 => Ok(VideoQualityAndFormatPreferences::SmallestSize),
-                2 => convert_to_format(term, media_selected),
+                0 => Ok((VideoQualityAndFormatPreferences::BestQuality, None)),
+                1 => Ok((VideoQualityAndFormatPreferences::SmallestSize, None)),
+                2 => Ok((convert_to_format(term, media_selected)?, None)),
                 _ => get_format_from_yt(term, url, media_selected),
             }
         } else {
@@ -147,8 +178,8 @@ mod format {
                 .items(&format_options)
                 .interact_on(term)?;
             match user_selection {
-                0 => Ok(VideoQualityAndFormatPreferences::BestQuality),
-                1 => Ok(VideoQualityAndFormatPreferences::SmallestSize),
+                0 => Ok((VideoQualityAndFormatPreferences::BestQuality, None)),
+                1 => Ok((VideoQualityAndFormatPreferences::SmallestSize, None)),
                 _ => get_format_from_yt(term, url, media_selected),
             }
         }
@@ -156,7 +187,7 @@ mod format {
 
     // Show the user a list of formats common across the whole playlist, picked from those available directly from yt.
     fn get_format_from_yt(term: &Term, url: &str, media_selected: &MediaSelection)
-                          -> BlobResult<VideoQualityAndFormatPreferences>
+                          -> BlobResult<(VideoQualityAndFormatPreferences, Option<Vec<FormatFallback>>)>
     {
         // Get a list of all the formats available for the playlist
         let ytdl_formats = get_ytdlp_formats(url)?;
@@ -171,6 +202,8 @@ mod format {
 
         // Ids which the user can pick according to the current media selection (VideoOnly / AudioOnly / FullVideo)
         let mut correct_ids = vec![];
+        // What to download when a video doesn't have the chosen format, for each of the ids
+        let mut fallbacks = vec![];
         // Format options that will be shown to the user
         let mut ui_format_options = vec![];
 
@@ -184,6 +217,7 @@ mod format {
                         // Add to the list of available formats the current one formatted in a nice way
                         ui_format_options.push(format.to_string());
                         correct_ids.push(id);
+                        fallbacks.push(FormatFallback::chain_for(format, media_selected));
                     }
                 }
             }
@@ -195,7 +229,7 @@ mod format {
             .items(&ui_format_options)
             .interact_on(term)?;
 
-        Ok(VideoQualityAndFormatPreferences::UniqueFormat(correct_ids[user_selection].clone()))
+        Ok((VideoQualityAndFormatPreferences::UniqueFormat(correct_ids[user_selection].clone()), Some(fallbacks.swap_remove(user_selection))))
     }
 
     /// All the formats for all the videos in a playlist
@@ -26,24 +26,17 @@ pub(crate) fn assemble_data(url: &str, playlist_id: usize, user_config: youtube:
         media_selected = get_media_selection(&term)?;
     }
     
-    let chosen_format;
-    if let Some(format) = user_config.chosen_format {
-        // With config files it is possible to "force" blob-dl to try to use ffmpeg
-        if let VideoQualityAndFormatPreferences::ConvertTo(_) = format {
-            // The user wants their files to be converted to another format
-            if !which("ffmpeg").is_ok() {
-                // The conversion cannot be performed because ffmpeg is not installed
-                chosen_format = format::get_format(&term, url, &media_selected, playlist_id)?;
-            } else {
-                // ffmpeg is installed so what was specified in the config file can be used
-                chosen_format = format;
-            }
-        } else {
-            chosen_format = format;
-        }
-    } else {
-        chosen_format = format::get_format(&term, url, &media_selected, playlist_id)?;
-    }
+    let (chosen_format, picked_fallback) = match user_config.chosen_format {
+        // With config files it is possible to "force" blob-dl to try to use ffmpeg,
+        // the conversion cannot be performed if ffmpeg is not installed
+        Some(VideoQualityAndFormatPreferences::ConvertTo(_)) if which("ffmpeg").is_err() => format::get_format(&term, url, &media_selected, playlist_id)?,
+        // What was specified in the config file can be used
+        Some(format) => (format, None),
+        // Config file didn't say anything about file formats
+        None => format::get_format(&term, url, &media_selected, playlist_id)?,
+    };
+    // Fallbacks from the config file win over the ones which suit the format picked from yt-dlp's list
+    let format_fallback = user_config.format_fallback.or(picked_fallback);
 
     let output_path;
     if let Some(path) = user_config.output_path {
@@ -61,12 +54,71 @@ pub(crate) fn assemble_data(url: &str, playlist_id: usize, user_config: youtube:
         playlist_index = playlist_id;
     }
     
+    let split_by_tracklist;
+    if let Some(split) = user_config.split_by_tracklist {
+        split_by_tracklist = split;
+    } else if media_selected == MediaSelection::AudioOnly && which("ffmpeg").is_ok() {
+        // Splitting is done with ffmpeg and only makes sense for audio files
+        split_by_tracklist = get_tracklist_preference(&term)?;
+    } else {
+        split_by_tracklist = false;
+    }
+    
+    let sponsorblock;
+    if let Some(preference) = user_config.sponsorblock {
+        sponsorblock = preference;
+    } else if which("ffmpeg").is_ok() {
+        sponsorblock = get_sponsorblock_preference(&term)?;
+    } else {
+        // Both removing and marking segments are done by ffmpeg
+        sponsorblock = SponsorBlockPreferences::Disabled;
+    }
+    
+    // Links to a specific moment of the video (t= or start=) download it from there on, unless ranges were given explicitly
+    let start_time = analyzer::start_time(url).filter(|start| *start > 0);
+    let sections;
+    if let Some(config_sections) = user_config.sections.clone().filter(|sections| !sections.is_empty()) {
+        sections = config_sections;
+    } else if which("ffmpeg").is_err() {
+        // yt-dlp needs ffmpeg to download only parts of a video
+        if let Some(start) = start_time {
+            println!("{} The link starts at {}, but that is ignored because ffmpeg isn't installed: the whole video will be downloaded",
+                     "[blob-dl]".purple(),
+                     crate::tracklist::format_timestamp(start));
+        }
+        sections = vec![];
+    } else if let Some(start) = start_time {
+        println!("{} The link starts at {}, the video will be downloaded from there on",
+                 "[blob-dl]".purple(),
+                 crate::tracklist::format_timestamp(start));
+        sections = vec![ClipSection { start, end: None }];
+    } else if let Some(config_sections) = user_config.sections {
+        // The config file asks for the whole video
+        sections = config_sections;
+    } else {
+        sections = get_clip_sections(&term)?;
+    }
+
+    let force_keyframes_at_cuts;
+    if let Some(keyframes) = user_config.force_keyframes_at_cuts {
+        force_keyframes_at_cuts = keyframes;
+    } else if !sections.is_empty() {
+        force_keyframes_at_cuts = get_keyframes_preference(&term)?;
+    } else {
+        force_keyframes_at_cuts = false;
+    }
+    
     Ok(config::DownloadConfig::new_video(
         url,
         chosen_format,
+        format_fallback,
         output_path,
         media_selected,
         playlist_index,
+        split_by_tracklist,
+        sponsorblock,
+        sections,
+        force_keyframes_at_cuts,
     ))
 }
 
@@ -78,7 +130,7 @@ mod format {
     ///
     /// The options are filtered between video, audio-only and video-only
     pub(super) fn get_format(term: &Term, url: &str, media_selected: &MediaSelection, playlist_id: usize)
-                             -> BlobResult<VideoQualityAndFormatPreferences>
+                             -> BlobResult<(VideoQualityAndFormatPreferences, Option<Vec<FormatFallback>>)>
     {
         // A list of all the format options that can be picked
         let mut format_options: Vec<&str> = vec![];
@@ -101,9 +153,9 @@ mod format {
                 .items(&format_options)
                 .interact_on(term)?;
             match user_selection {
-                0 => Ok(VideoQualityAndFormatPreferences::BestQuality),
-                1 => Ok(VideoQualityAndFormatPreferences::SmallestSize),
-                2 => convert_to_format(term, media_selected),
+                0 => Ok((VideoQualityAndFormatPreferences::BestQuality, None)),
+                1 => Ok((VideoQualityAndFormatPreferences::SmallestSize, None)),
+                2 => Ok((convert_to_format(term, media_selected)?, None)),
                 _ => get_format_from_yt(term, url, media_selected, playlist_id),
             }
         } else {
@@ -120,8 +172,8 @@ mod format {
 
             // See individual function documentations for more context
             match user_selection {
-                0 => Ok(VideoQualityAndFormatPreferences::BestQuality),
-                1 => Ok(VideoQualityAndFormatPreferences::SmallestSize),
+                0 => Ok((VideoQualityAndFormatPreferences::BestQuality, None)),
+                1 => Ok((VideoQualityAndFormatPreferences::SmallestSize, None)),
                 _ => get_format_from_yt(term, url, media_selected, playlist_id),
             }
         }
@@ -129,7 +181,7 @@ mod format {
 
     /// Presents the user with the formats youtube provides directly for download, without the need for ffmpeg
     fn get_format_from_yt(term: &Term, url: &str, media_selected: &MediaSelection, playlist_id: usize)
-                          -> BlobResult<VideoQualityAndFormatPreferences>
+                          -> BlobResult<(VideoQualityAndFormatPreferences, Option<Vec<FormatFallback>>)>
     {
         // Serialize all available formats from the youtube API (through yt-dlp -F)
         let serialized_formats = {
@@ -148,6 +200,8 @@ mod format {
 
         // Ids which the user can pick according to the current media selection
         let mut correct_ids = vec![];
+        // What to download when a video doesn't have the chosen format, for each of the ids
+        let mut fallbacks = vec![];
         // Every format which conforms to media_selected will be pushed here
         let mut format_options = vec![];
 
@@ -159,6 +213,7 @@ mod format {
                 format_options.push(format.to_string());
                 // Update the list of ids which match what the user wants
                 correct_ids.push(format.format_id.clone());
+                fallbacks.push(FormatFallback::chain_for(format, media_selected));
             }
         }
 
@@ -170,6 +225,6 @@ mod format {
             .interact_on(term)?;
 
         // Return the format corresponding to what the user selected, the choices are limited so there shouldn't be out-of-bounds problems
-        Ok(VideoQualityAndFormatPreferences::UniqueFormat(correct_ids[user_selection].clone()))
+        Ok((VideoQualityAndFormatPreferences::UniqueFormat(correct_ids[user_selection].clone()), Some(fallbacks.swap_remove(user_selection))))
     }
 }
\ No newline at end of file
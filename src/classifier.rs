@@ -0,0 +1,116 @@
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BlobResult, BlobdlError, ErrorCategory};
+
+/// How blob-dl recognizes the errors yt-dlp prints, the first rule whose pattern matches an error's message wins.
+///
+/// yt-dlp and youtube change their wording from time to time, so the patterns only look for the important words
+const BUILTIN_RULES: [(&str, ErrorCategory); 11] = [
+    // See YtdlpError::stopped()
+    (r"blob-dl stopped yt-dlp", ErrorCategory::TimedOut),
+    (r"(?i)private video|video is private", ErrorCategory::Private),
+    (r"(?i)confirm your age|age[- ]restricted|inappropriate for some users", ErrorCategory::AgeRestricted),
+    (r"(?i)members[- ]only|join this channel|available to this channel's members", ErrorCategory::MembersOnly),
+    (r"(?i)available in your (country|location)|blocked it in your country|geo[- ]?restrict", ErrorCategory::GeoBlocked),
+    (r"(?i)copyright", ErrorCategory::Copyright),
+    (r"(?i)HTTP Error (403|429)|too many requests|rate[- ]limit", ErrorCategory::Throttled),
+    (r"(?i)timed out|connection (reset|refused|aborted)|temporary failure in name resolution|name or service not known|network is unreachable|remote end closed connection|urlopen error|IncompleteRead|HTTP Error 5\d\d",
+     ErrorCategory::Network),
+    (r"(?i)requested format (is )?not available|no video formats found", ErrorCategory::FormatUnavailable),
+    (r"(?i)postprocessing|ffmpeg|ffprobe|conversion failed|error selecting an encoder", ErrorCategory::Postprocessing),
+    (r"(?i)video unavailable|removed|does not exist|not found|no longer available|incomplete data received|account .*terminated",
+     ErrorCategory::Removed),
+];
+
+/// A rule from the config file: errors whose message matches `pattern` (a regular expression) belong to `category`.
+///
+/// They are checked before blob-dl's own rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ErrorRule {
+    pub(crate) pattern: String,
+    pub(crate) category: ErrorCategory,
+}
+
+/// An ErrorRule whose pattern was compiled
+#[derive(Debug, Clone)]
+pub(crate) struct Rule {
+    regex: Regex,
+    category: ErrorCategory,
+}
+
+fn builtin_rules() -> &'static [Rule] {
+    static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        BUILTIN_RULES.iter()
+            .map(|(pattern, category)| Rule {
+                regex: Regex::new(pattern).expect("blob-dl's own error patterns are valid"),
+                category: *category,
+            })
+            .collect()
+    })
+}
+
+/// Compiles the user's rules, a pattern which isn't a valid regular expression is an error
+pub(crate) fn compile(rules: Option<&[ErrorRule]>) -> BlobResult<Vec<Rule>> {
+    rules.unwrap_or_default().iter()
+        .map(|rule| match Regex::new(&rule.pattern) {
+            Ok(regex) => Ok(Rule { regex, category: rule.category }),
+            Err(err) => Err(BlobdlError::InvalidErrorRule(err)),
+        })
+        .collect()
+}
+
+/// Sorts an error message into an ErrorCategory, `user_rules` take precedence over blob-dl's own rules
+pub(crate) fn classify(message: &str, user_rules: &[Rule]) -> ErrorCategory {
+    user_rules.iter()
+        .chain(builtin_rules())
+        .find(|rule| rule.regex.is_match(message))
+        .map(|rule| rule.category)
+        .unwrap_or(ErrorCategory::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_messages() {
+        let messages = [
+            (" Private video. Sign in if you've been granted access to this video", ErrorCategory::Private),
+            (" YouTube said: The playlist does not exist.", ErrorCategory::Removed),
+            (" The channel/playlist does not exist and the URL redirected to youtube.com home page", ErrorCategory::Removed),
+            (" Unable to download API page: <urlopen error [Errno -3] Temporary failure in name resolution> (caused by URLError(gaierror(-3, 'Temporary failure in name resolution')))", ErrorCategory::Network),
+            (" This video has been removed for violating YouTube's policy on violent or graphic content", ErrorCategory::Removed),
+            (" Video unavailable. This video has been removed by the uploader", ErrorCategory::Removed),
+            (" not found, unable to continue", ErrorCategory::Removed),
+            (" error: HTTP Error 403: Forbidden. Giving up after 10 retries", ErrorCategory::Throttled),
+            (" Unable to download API page: HTTP Error 404: Not Found (caused by <HTTPError 404: 'Not Found'>)", ErrorCategory::Removed),
+            (" Postprocessing: Error selecting an encoder for stream 0:1", ErrorCategory::Postprocessing),
+            ("Incomplete data received", ErrorCategory::Removed),
+            (" Requested format is not available. Use --list-formats for a list of available formats", ErrorCategory::FormatUnavailable),
+            (" Video unavailable. This video is no longer available due to a copyright claim by Someone", ErrorCategory::Copyright),
+            (" Sign in to confirm your age. This video may be inappropriate for some users.", ErrorCategory::AgeRestricted),
+            (" Join this channel to get access to members-only content like this video, and other exclusive perks.", ErrorCategory::MembersOnly),
+            (" Video unavailable. The uploader has not made this video available in your country", ErrorCategory::GeoBlocked),
+            (" Unable to download webpage: HTTP Error 429: Too Many Requests", ErrorCategory::Throttled),
+            (" Read timed out.", ErrorCategory::Network),
+            (" blob-dl stopped yt-dlp because it made no progress for 60s", ErrorCategory::TimedOut),
+            (" Something nobody has seen before", ErrorCategory::Unknown),
+        ];
+
+        for (message, category) in messages {
+            assert_eq!(classify(message, &[]), category, "{}", message);
+        }
+    }
+
+    #[test]
+    fn user_rules_come_first() {
+        let rules = compile(Some(&[ErrorRule { pattern: String::from("(?i)not a bot"), category: ErrorCategory::Throttled }])).unwrap();
+        assert_eq!(classify(" Sign in to confirm you're not a bot", &rules), ErrorCategory::Throttled);
+        assert_eq!(classify(" Private video", &rules), ErrorCategory::Private);
+
+        assert!(compile(Some(&[ErrorRule { pattern: String::from("(unclosed"), category: ErrorCategory::Network }])).is_err());
+    }
+}
@@ -1,8 +1,15 @@
 use crate::analyzer;
 use crate::parser;
 use crate::assembling;
+use crate::app::ExitStatus;
 use crate::error::{BlobResult, BlobdlError};
 use crate::run;
+use crate::archive;
+use crate::sync;
+use crate::job::Job;
+use crate::history;
+use crate::watch;
+use crate::queue;
 
 use directories::ProjectDirs;
 use std::fs::{self};
@@ -13,47 +20,63 @@ use crate::assembling::youtube;
 use crate::parser::ConfigFilePreferences;
 
 /// Calls the builder function according to what the url refers to (video/playlist), then it runs the ytdl-command and handles errors
-pub fn dispatch(cli_config: &parser::CliConfig) -> BlobResult<()> {
+pub fn dispatch(cli_config: &parser::CliConfig) -> BlobResult<ExitStatus> {
+    if let Some(subcommand) = cli_config.subcommand() {
+        return run_subcommand(subcommand, cli_config);
+    }
+
     // Whether a new config file should be generated
     let mut should_generate_config = false;
     
     // user_config is created with data from the config file. Once execution
     // reaches the point where questions need to be asked to the user, data which is already
     // present in user_config is used instead of being asked the user directly
-    let user_config = match cli_config.config_file_preference() {
-        // There is no config file
-        ConfigFilePreferences::NoConfig => youtube::config::DownloadConfig::empty(),
-        // The config file is in blob-dl's default location
-        ConfigFilePreferences::DefaultConfig => {
-            let path = get_config_path().ok_or(BlobdlError::ConfigFileNotFound)?;
-            read_config(&path)?
-        }
-        ConfigFilePreferences::CustomConfig(custom_path) => {
-            read_config(custom_path)?
-        },
-        
+    let mut user_config = match cli_config.config_file_preference() {
         ConfigFilePreferences::GenerateConfig => {
             println!("{} A config file based on your answers will be generated", "[blob-dl]".purple());
             should_generate_config = true;
             youtube::config::DownloadConfig::empty()
         }
+        preference => load_user_config(preference)?.unwrap_or_else(youtube::config::DownloadConfig::empty),
     };
     
+    // Clips passed as command line arguments take precedence over the config file
+    if !cli_config.clip_sections().is_empty() {
+        user_config.sections = Some(cli_config.clip_sections().clone());
+    }
+    if let Some(jobs) = cli_config.jobs() {
+        user_config.advanced.parallel_downloads = Some(jobs);
+    }
+    if let Some(max_attempts) = cli_config.max_attempts() {
+        // The rest of the policy comes from the config file, if it has one
+        let mut retry = user_config.advanced.retry.take().unwrap_or_default();
+        retry.max_attempts = max_attempts;
+        user_config.advanced.retry = Some(retry);
+    }
+    if let Some(download_archive) = cli_config.download_archive() {
+        user_config.advanced.download_archive = Some(download_archive.clone());
+    }
+    if cli_config.timeout().is_some() || cli_config.stall_timeout().is_some() {
+        // A limit which is only in the config file still applies
+        let mut supervision = user_config.advanced.supervision.take().unwrap_or_default();
+        supervision.timeout = cli_config.timeout().map(|seconds| seconds as f64).or(supervision.timeout);
+        supervision.stall_timeout = cli_config.stall_timeout().map(|seconds| seconds as f64).or(supervision.stall_timeout);
+        user_config.advanced.supervision = Some(supervision);
+    }
+
     // Parse what the url refers to
     let download_option = analyzer::analyze_url(cli_config.url())?;
 
     // Generate a command according to the user's preferences
-    let mut command_and_download_config = assembling::generate_command(cli_config.url(), &download_option, user_config)? ;
-
-    if cli_config.show_command() {
-        println!("Command generated by blob-dl: {:?}", command_and_download_config.0);
-    }
+    let command_and_download_config = assembling::generate_command(cli_config.url(), &download_option, user_config)? ;
 
     if should_generate_config {
         // Currently config-files cannot be generated in an arbitrary location
         if let Some(path) = get_config_path() {
             let mut tmp = command_and_download_config.1.clone();
             tmp.url = None;
+            // Clips only make sense for the video they were chosen for
+            tmp.sections = None;
             write_config(path.clone(), &tmp)?;
             println!("{} Successfully created a config file in {}", "[blob-dl]".purple(), path.display());
         } else {
@@ -61,10 +84,96 @@ pub fn dispatch(cli_config: &parser::CliConfig) -> BlobResult<()> {
         }
     }
     
+    let (command, download_config) = command_and_download_config;
+    let mut job = Job::create(cli_config.job_name(), download_config, cli_config.replace_job())?;
+
+    if cli_config.show_command() {
+        println!("Command generated by blob-dl: {:?}", command);
+    }
+
     // Run the command
-    run::run_and_observe(&mut command_and_download_config.0, &command_and_download_config.1, cli_config.verbosity())?;
+    run::run_and_observe(command, &mut job, cli_config.verbosity(), cli_config.report())
+}
 
-    Ok(())
+/// Runs the commands which do something other than a plain download.
+///
+/// The ones which don't download anything succeed unless they return an error
+fn run_subcommand(subcommand: &parser::CliSubcommand, cli_config: &parser::CliConfig) -> BlobResult<ExitStatus> {
+    match subcommand {
+        parser::CliSubcommand::Archive { location, remove, prune } => {
+            archive::run_subcommand(location.as_ref(), remove, *prune).map(|_| ExitStatus::Success)
+        }
+        parser::CliSubcommand::Sync { url, directory, dry_run, removed, jobs, config_file_preference } => {
+            let options = sync::SyncOptions {
+                url,
+                directory,
+                dry_run: *dry_run,
+                removed,
+                jobs: *jobs,
+                verbosity: cli_config.verbosity(),
+                report: cli_config.report(),
+            };
+            sync::sync(&options, load_user_config(config_file_preference)?)
+        }
+        parser::CliSubcommand::Resume { job } => {
+            let mut job = Job::load(job.as_deref())?;
+            println!("{} Resuming job {}", "[blob-dl]".purple(), job.state.name);
+            let command = job.state.download_config.build_command()?.0;
+            run::run_and_observe(command, &mut job, cli_config.verbosity(), cli_config.report())
+        }
+        parser::CliSubcommand::History { filter, show } => match show {
+            Some(id) => history::show(*id),
+            None => history::list(filter),
+        }.map(|_| ExitStatus::Success),
+        parser::CliSubcommand::RetryFailed { job } => {
+            let mut job = Job::load(job.as_deref())?;
+            run::retry_job_failures(&mut job, cli_config.verbosity(), cli_config.report())
+        }
+        parser::CliSubcommand::Watch { urls, interval, once, jobs, config_file_preference } => {
+            let options = watch::WatchOptions {
+                urls,
+                interval: *interval,
+                once: *once,
+                jobs: *jobs,
+                verbosity: cli_config.verbosity(),
+                report: cli_config.report(),
+            };
+            watch::watch(&options, load_user_config(config_file_preference)?)
+        }
+        parser::CliSubcommand::Queue(action) => match action {
+            parser::QueueAction::Add { url, config_file_preference } => {
+                let profile = match config_file_preference {
+                    ConfigFilePreferences::Profile(name) => Some(name.as_str()),
+                    _ => None,
+                };
+                queue::add(url, profile, load_user_config(config_file_preference)?).map(|_| ExitStatus::Success)
+            }
+            parser::QueueAction::List => queue::list().map(|_| ExitStatus::Success),
+            parser::QueueAction::Remove { ids } => queue::remove(ids).map(|_| ExitStatus::Success),
+            parser::QueueAction::Run => queue::run(cli_config.verbosity(), cli_config.report()),
+        },
+    }
+}
+
+/// Reads the config file the user asked for, None if they didn't ask for one
+fn load_user_config(preference: &ConfigFilePreferences) -> BlobResult<Option<youtube::config::DownloadConfig>> {
+    match preference {
+        // The config file is in blob-dl's default location
+        ConfigFilePreferences::DefaultConfig => {
+            let path = get_config_path().ok_or(BlobdlError::ConfigFileNotFound)?;
+            read_config(&path).map(Some)
+        }
+        ConfigFilePreferences::CustomConfig(custom_path) => read_config(custom_path).map(Some),
+        ConfigFilePreferences::Profile(name) => {
+            let path = get_profile_path(name).ok_or(BlobdlError::ConfigFileNotFound)?;
+            if !path.exists() {
+                return Err(BlobdlError::ProfileNotFound);
+            }
+            read_config(&path).map(Some)
+        }
+        // There is no config file
+        ConfigFilePreferences::NoConfig | ConfigFilePreferences::GenerateConfig => Ok(None),
+    }
 }
 
 // Functions to handle config files
@@ -74,6 +183,19 @@ fn get_config_path() -> Option<PathBuf> {
         .map(|dirs| dirs.config_dir().join("config.json"))
 }
 
+/// Profiles are config files kept next to the default one, in a `profiles` folder
+fn get_profile_path(name: &str) -> Option<PathBuf> {
+    get_config_path()
+        .and_then(|path| path.parent().map(|dir| dir.join("profiles").join(format!("{}.json", name))))
+}
+
+/// Where blob-dl keeps its own data (download archive, jobs, ...), it depends on what operating system blob-dl is running on
+pub(crate) fn get_data_dir() -> BlobResult<PathBuf> {
+    ProjectDirs::from("", "", "blob-dl")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .ok_or(BlobdlError::DataDirNotFound)
+}
+
 /// This will create a new config file (or overwrite an old one) with the DownloadConfig that is passed in.
 /// 
 /// The path needs to also be passed in because the user could put their config file in an unexpected place and
@@ -97,6 +219,7 @@ fn write_config(path: PathBuf, download_config: &youtube::config::DownloadConfig
 /// Create a DownloadConfig object from the contents of the config file
 // TODO Tell the user if this returns None (no config file was found, but blob-dl will still work as normal)
 fn read_config(config_file_path: &PathBuf) -> BlobResult<youtube::config::DownloadConfig> {
-    let contents = fs::read_to_string(config_file_path)?;
-    serde_json::from_str(&contents).map_err(|err| {BlobdlError::SerdeError(err)})
+    let contents = fs::read_to_string(config_file_path)
+        .map_err(|err| BlobdlError::InvalidConfigFile(format!("{}: {}", config_file_path.display(), err)))?;
+    serde_json::from_str(&contents).map_err(|err| BlobdlError::InvalidConfigFile(format!("{}: {}", config_file_path.display(), err)))
 }
\ No newline at end of file
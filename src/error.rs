@@ -1,11 +1,17 @@
 use std::io::Write;
 use crate::blobdl_error_message::*;
 use crate::ui_prompts::*;
+use crate::classifier;
+use crate::app::ExitStatus;
 
 use colored::Colorize;
+use serde::{Deserialize, Serialize};
 
 pub type BlobResult<T> = Result<T, BlobdlError>;
 
+/// The beginning of the message of errors which blob-dl itself caused by stopping yt-dlp, see YtdlpError::stopped()
+const STOPPED_BY_BLOBDL: &str = "blob-dl stopped yt-dlp";
+
 /// ### The all-encompassing error type used in this project
 /// ## Implements From
 /// For the Errors std::io::Error and ParseIntError
@@ -42,68 +48,171 @@ pub enum BlobdlError {
     
     ConfigFileNotFound,
     JsonGenerationError,
+    // ffmpeg couldn't cut a file into the tracks listed in its description
+    TracklistSplitError,
+    // A time range passed with --clip couldn't be parsed
+    InvalidClipSection,
+    // The operating system didn't say where blob-dl can keep its data (such as the global download archive)
+    DataDirNotFound,
+    // `blob-dl resume` or `blob-dl retry-failed` couldn't find the job they were asked to continue
+    JobNotFound,
+    // `blob-dl history show` was given an id which isn't in the history
+    HistoryRecordNotFound,
+    // The first value passed to --report wasn't json or markdown
+    UnknownReportFormat,
+    // The operating system refused to start a yt-dlp process
+    YtdlpNotStarted(std::io::Error),
+    // A pattern in the config file's error_rules isn't a valid regular expression
+    InvalidErrorRule(regex::Error),
+    // `blob-dl queue remove` was given an id which isn't in the queue
+    QueueEntryNotFound,
+    // --profile was given the name of a profile which doesn't exist
+    ProfileNotFound,
+    // The config file doesn't exist or isn't valid json, the String says why
+    InvalidConfigFile(String),
+    // The name passed to --job (or to resume/retry-failed) would make the job's file end up outside the jobs directory
+    InvalidJobName,
+    // --job was given the name of an existing job without --replace-job
+    JobAlreadyExists,
+    // --output-format json was asked for on a platform where stdout can't be kept for events
+    JsonOutputUnsupported,
 }
 
 impl BlobdlError {
     // Output an error message according to the error at hand
     pub fn report(&self) {
+        // This should not be treated as a program-ending error
+        if let BlobdlError::CommandNotSpawned = self {
+            return;
+        }
         //eprintln!("\n{}\n", USAGE_MSG);
         eprint!("{}: ", "ERROR".red());
 
         let _ = std::io::stdout().flush();
 
+        eprintln!("{}", self.message());
+        eprintln!("{}", SEE_HELP_PAGE);
+    }
+
+    /// What went wrong, in words the user can understand
+    pub(crate) fn message(&self) -> String {
         match self {
-            BlobdlError::QueryNotFound => eprintln!("{}", BROKEN_URL_ERR),
+            BlobdlError::QueryNotFound => BROKEN_URL_ERR.to_string(),
 
-            BlobdlError::UnknownUrl => eprintln!("{}", BROKEN_URL_ERR),
+            BlobdlError::UnknownUrl => BROKEN_URL_ERR.to_string(),
 
-            BlobdlError::UnsupportedWebsite => eprintln!("{}", UNSUPPORTED_WEBSITE_ERR),
+            BlobdlError::UnsupportedWebsite => UNSUPPORTED_WEBSITE_ERR.to_string(),
 
-            BlobdlError::DomainNotFound => eprintln!("{}", BROKEN_URL_ERR),
+            BlobdlError::DomainNotFound => BROKEN_URL_ERR.to_string(),
 
             // The link appears to be completely broken
-            BlobdlError::UrlParsingError => eprintln!("{}", BROKEN_URL_ERR),
+            BlobdlError::UrlParsingError => BROKEN_URL_ERR.to_string(),
 
-            BlobdlError::UnknownIssue => eprintln!("{}", UNKNOWN_ISSUE_ERR),
+            BlobdlError::UnknownIssue => UNKNOWN_ISSUE_ERR.to_string(),
 
-            BlobdlError::MissingArgument => eprintln!("{}", MISSING_ARGUMENT_ERR),
+            BlobdlError::MissingArgument => MISSING_ARGUMENT_ERR.to_string(),
 
-            BlobdlError::JsonSerializationError => eprintln!("{}", JSON_SERIALIZATION_ERR),
+            BlobdlError::JsonSerializationError => JSON_SERIALIZATION_ERR.to_string(),
 
-            BlobdlError::Utf8Error => eprintln!("{}", UTF8_ERR),
+            BlobdlError::Utf8Error => UTF8_ERR.to_string(),
 
-            BlobdlError::SerdeError(err) => eprintln!("{} {}", SERDE_ERR, err),
+            BlobdlError::SerdeError(err) => format!("{} {}", SERDE_ERR, err),
 
-            BlobdlError::IoError(err) => eprintln!("{} {}", IO_ERR, err),
+            BlobdlError::IoError(err) => format!("{} {}", IO_ERR, err),
 
-            BlobdlError::QueryCouldNotBeParsed => eprintln!("{}", URL_QUERY_COULD_NOT_BE_PARSED),
+            BlobdlError::QueryCouldNotBeParsed => URL_QUERY_COULD_NOT_BE_PARSED.to_string(),
 
-            BlobdlError::UrlIndexParsingError => eprintln!("{}", URL_INDEX_PARSING_ERR),
+            BlobdlError::UrlIndexParsingError => URL_INDEX_PARSING_ERR.to_string(),
 
-            BlobdlError::PlaylistUrlError => eprintln!("{}", PLAYLIST_URL_ERROR),
+            BlobdlError::PlaylistUrlError => PLAYLIST_URL_ERROR.to_string(),
 
-            // Early return because this should not be treated as a program-ending error
-            BlobdlError::CommandNotSpawned => return,
+            BlobdlError::CommandNotSpawned => COMMAND_NOT_SPAWNED.to_string(),
 
-            BlobdlError::UrlNotProvided => eprintln!("{}", URL_NOT_PROVIDED_ERROR),
+            BlobdlError::UrlNotProvided => URL_NOT_PROVIDED_ERROR.to_string(),
             
-            BlobdlError::FormatPreferenceNotProvided => eprintln!("{}", FORMAT_PREFERENCE_NOT_PROVIDED_ERROR),
+            BlobdlError::FormatPreferenceNotProvided => FORMAT_PREFERENCE_NOT_PROVIDED_ERROR.to_string(),
             
-            BlobdlError::OutputPathNotProvided => eprintln!("{}", OUTPUT_PATH_NOT_PROVIDED_ERROR),
+            BlobdlError::OutputPathNotProvided => OUTPUT_PATH_NOT_PROVIDED_ERROR.to_string(),
             
-            BlobdlError::DownloadTargetNotProvided => eprintln!("{}", DOWNLOAD_TARGET_NOT_PROVIDED_ERROR),    
+            BlobdlError::DownloadTargetNotProvided => DOWNLOAD_TARGET_NOT_PROVIDED_ERROR.to_string(),    
             
-            BlobdlError::IncludeIndexesNotProvided => eprintln!("{}", INCLUDE_INDEXES_NOT_PROVIDED_ERROR),    
+            BlobdlError::IncludeIndexesNotProvided => INCLUDE_INDEXES_NOT_PROVIDED_ERROR.to_string(),    
             
-            BlobdlError::MediaSelectedNotProvided => eprintln!("{}", MEDIA_SELECTION_NOT_PROVIDED_ERROR),
+            BlobdlError::MediaSelectedNotProvided => MEDIA_SELECTION_NOT_PROVIDED_ERROR.to_string(),
             
-            BlobdlError::ChosenFormatNotProvided => eprintln!("{}", CHOSEN_FORMAT_NOT_PROVIDED_ERROR),
+            BlobdlError::ChosenFormatNotProvided => CHOSEN_FORMAT_NOT_PROVIDED_ERROR.to_string(),
             
-            BlobdlError::ConfigFileNotFound => eprintln!("{}", CONFIG_FILE_NOT_FOUND_ERR),
+            BlobdlError::ConfigFileNotFound => CONFIG_FILE_NOT_FOUND_ERR.to_string(),
             
-            BlobdlError::JsonGenerationError => eprintln!("{}", JSON_GENERATION_ERR),
+            BlobdlError::JsonGenerationError => JSON_GENERATION_ERR.to_string(),
+
+            BlobdlError::TracklistSplitError => TRACKLIST_SPLIT_ERR.to_string(),
+
+            BlobdlError::InvalidClipSection => INVALID_CLIP_SECTION_ERR.to_string(),
+
+            BlobdlError::DataDirNotFound => DATA_DIR_NOT_FOUND_ERR.to_string(),
+
+            BlobdlError::JobNotFound => JOB_NOT_FOUND_ERR.to_string(),
+
+            BlobdlError::HistoryRecordNotFound => HISTORY_RECORD_NOT_FOUND_ERR.to_string(),
+
+            BlobdlError::UnknownReportFormat => UNKNOWN_REPORT_FORMAT_ERR.to_string(),
+
+            BlobdlError::YtdlpNotStarted(err) => format!("{} {}", YTDLP_NOT_STARTED_ERR, err),
+
+            BlobdlError::InvalidErrorRule(err) => format!("{} {}", INVALID_ERROR_RULE_ERR, err),
+
+            BlobdlError::QueueEntryNotFound => QUEUE_ENTRY_NOT_FOUND_ERR.to_string(),
+
+            BlobdlError::ProfileNotFound => PROFILE_NOT_FOUND_ERR.to_string(),
+
+            BlobdlError::InvalidConfigFile(reason) => format!("{} {}", INVALID_CONFIG_FILE_ERR, reason),
+
+            BlobdlError::InvalidJobName => INVALID_JOB_NAME_ERR.to_string(),
+
+            BlobdlError::JobAlreadyExists => JOB_ALREADY_EXISTS_ERR.to_string(),
+
+            BlobdlError::JsonOutputUnsupported => JSON_OUTPUT_UNSUPPORTED_ERR.to_string(),
+        }
+    }
+
+    /// The exit status blob-dl ends with when it stops because of this error
+    pub(crate) fn exit_status(&self) -> ExitStatus {
+        match self {
+            // The url, the command line or the config file are wrong
+            BlobdlError::QueryNotFound
+            | BlobdlError::UnknownUrl
+            | BlobdlError::UnsupportedWebsite
+            | BlobdlError::DomainNotFound
+            | BlobdlError::UrlParsingError
+            | BlobdlError::MissingArgument
+            | BlobdlError::UrlIndexParsingError
+            | BlobdlError::QueryCouldNotBeParsed
+            | BlobdlError::PlaylistUrlError
+            | BlobdlError::UrlNotProvided
+            | BlobdlError::FormatPreferenceNotProvided
+            | BlobdlError::OutputPathNotProvided
+            | BlobdlError::DownloadTargetNotProvided
+            | BlobdlError::IncludeIndexesNotProvided
+            | BlobdlError::MediaSelectedNotProvided
+            | BlobdlError::ChosenFormatNotProvided
+            | BlobdlError::ConfigFileNotFound
+            | BlobdlError::InvalidClipSection
+            | BlobdlError::JobNotFound
+            | BlobdlError::HistoryRecordNotFound
+            | BlobdlError::UnknownReportFormat
+            | BlobdlError::InvalidErrorRule(_)
+            | BlobdlError::QueueEntryNotFound
+            | BlobdlError::ProfileNotFound
+            | BlobdlError::InvalidConfigFile(_)
+            | BlobdlError::InvalidJobName
+            | BlobdlError::JobAlreadyExists
+            | BlobdlError::JsonOutputUnsupported => ExitStatus::UsageError,
+
+            BlobdlError::YtdlpNotStarted(err) if err.kind() == std::io::ErrorKind::NotFound => ExitStatus::MissingDependency,
+
+            _ => ExitStatus::Failure,
         }
-        eprintln!("{}", SEE_HELP_PAGE);
     }
 }
 
@@ -135,10 +244,15 @@ impl From<serde_json::Error> for BlobdlError {
 
 // Used in run.rs
 /// Stores the information found in yt-dlp's error-lines output
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct YtdlpError {
     video_id: String,
+    /// The tag yt-dlp put before the message (such as `youtube:tab`), if there was one
+    extractor: Option<String>,
     error_msg: String,
+    /// Where the video is in the playlist that was being downloaded, if any
+    playlist_index: Option<usize>,
+    category: ErrorCategory,
 }
 
 impl YtdlpError {
@@ -149,61 +263,267 @@ impl YtdlpError {
     pub fn error_msg(&self) -> &String {
         &self.error_msg
     }
+
+    pub fn playlist_index(&self) -> Option<usize> {
+        self.playlist_index
+    }
+
+    /// Associates the error with the item yt-dlp was working on when it occurred.
+    ///
+    /// Some error lines don't contain a video id, in that case the one of the current item is used
+    pub fn attribute_to(&mut self, video_id: Option<&str>, playlist_index: Option<usize>) {
+        if self.video_id == "unavailable" {
+            if let Some(id) = video_id {
+                self.video_id = id.to_string();
+            }
+        }
+        self.playlist_index = playlist_index;
+    }
+}
+
+/// A broad description of what made a download fail, used to decide whether trying again makes sense (see classifier.rs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ErrorCategory {
+    /// Connection problems and server errors, they usually go away on their own
+    Network,
+    /// YouTube refused to serve the video (HTTP 403) or asked to slow down (HTTP 429)
+    Throttled,
+    /// The video isn't available in the user's country
+    GeoBlocked,
+    /// YouTube wants the user to sign in to prove their age
+    AgeRestricted,
+    /// Only the channel's members can watch the video
+    MembersOnly,
+    Private,
+    /// The video was removed or doesn't exist
+    // Config and job files written before the categories were split up call it Unavailable
+    #[serde(alias = "Unavailable")]
+    Removed,
+    /// The video was taken down because of a copyright claim
+    Copyright,
+    /// The requested format isn't available for this video
+    FormatUnavailable,
+    /// ffmpeg ran into a problem while processing the downloaded file
+    Postprocessing,
+    /// yt-dlp took too long or stopped making progress, so blob-dl stopped it
+    TimedOut,
+    Unknown,
+}
+
+/// Every ErrorCategory, in the order they are listed to the user
+pub(crate) const ALL_CATEGORIES: [ErrorCategory; 12] = [
+    ErrorCategory::Network, ErrorCategory::Throttled, ErrorCategory::GeoBlocked, ErrorCategory::AgeRestricted,
+    ErrorCategory::MembersOnly, ErrorCategory::Private, ErrorCategory::Removed, ErrorCategory::Copyright,
+    ErrorCategory::FormatUnavailable, ErrorCategory::Postprocessing, ErrorCategory::TimedOut, ErrorCategory::Unknown,
+];
+
+impl ErrorCategory {
+    /// Whether trying again is likely to work, used by the default RetryPolicy and to choose which videos the user can re-download
+    pub(crate) fn retry_by_default(&self) -> bool {
+        matches!(self, ErrorCategory::Network | ErrorCategory::Throttled | ErrorCategory::TimedOut)
+    }
+
+    /// What the user can do about errors of this kind
+    pub(crate) fn hint(&self) -> &'static str {
+        match self {
+            ErrorCategory::Network => "Check your internet connection, trying again later usually works",
+            ErrorCategory::Throttled => "YouTube is limiting your downloads: wait a while, or download fewer videos at the same time",
+            ErrorCategory::GeoBlocked => "The video isn't available in your country",
+            ErrorCategory::AgeRestricted => "YouTube only shows this video to signed in users, yt-dlp needs your browser's cookies to download it",
+            ErrorCategory::MembersOnly => "Only the channel's members can download this video, yt-dlp needs the cookies of a member's account",
+            ErrorCategory::Private => "Only the accounts the video was shared with can download it",
+            ErrorCategory::Removed => "The video was removed or the link is wrong, there is nothing to download",
+            ErrorCategory::Copyright => "The video was taken down because of a copyright claim",
+            ErrorCategory::FormatUnavailable => "The chosen format isn't available for this video, try the best quality instead",
+            ErrorCategory::Postprocessing => "ffmpeg couldn't process the file, make sure that it is installed and up to date",
+            ErrorCategory::TimedOut => "blob-dl stopped the download, see `supervision` in the config file",
+            ErrorCategory::Unknown => "If this keeps happening please report it on blob-dl's GitHub page",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ErrorCategory::Network => "network error",
+            ErrorCategory::Throttled => "blocked by youtube",
+            ErrorCategory::GeoBlocked => "not available in your country",
+            ErrorCategory::AgeRestricted => "age-restricted",
+            ErrorCategory::MembersOnly => "members only",
+            ErrorCategory::Private => "private video",
+            ErrorCategory::Removed => "video unavailable",
+            ErrorCategory::Copyright => "copyright claim",
+            ErrorCategory::FormatUnavailable => "format unavailable",
+            ErrorCategory::Postprocessing => "postprocessing error",
+            ErrorCategory::TimedOut => "timed out",
+            ErrorCategory::Unknown => "unknown error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl YtdlpError {
+    pub(crate) fn category(&self) -> ErrorCategory {
+        self.category
+    }
+
+    /// Sorts the error again, taking the user's rules into account
+    pub(crate) fn classify(&mut self, user_rules: &[classifier::Rule]) {
+        self.category = classifier::classify(&self.error_msg, user_rules);
+    }
 }
 
 impl std::fmt::Display for YtdlpError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut result ;
         result = format!("{} {}", "yt-video id:", self.video_id);
-        result = format!("{}\n   {} {}\n", result, "Reason:", self.error_msg);
+        if let Some(index) = self.playlist_index {
+            result = format!("{} (#{} in the playlist)", result, index);
+        }
+        result = match self.extractor.as_deref() {
+            // Tags other than the usual one say where the error comes from
+            Some(tag) if tag != "youtube" => format!("{}\n   {} [{}]{}\n", result, "Reason:", tag, self.error_msg),
+            _ => format!("{}\n   {} {}\n", result, "Reason:", self.error_msg),
+        };
 
         write!(f, "{}", result)
     }
 }
 
 impl YtdlpError {
-    /// Parses a YtdlpError object from a ytdlp line which contains an error
+    /// The error of a video which blob-dl stopped downloading, `reason` says why
+    pub(crate) fn stopped(video_id: Option<&str>, playlist_index: Option<usize>, reason: &str) -> YtdlpError {
+        YtdlpError {
+            video_id: video_id.unwrap_or("unavailable").to_string(),
+            extractor: None,
+            // Messages parsed from yt-dlp's output start with a space too
+            error_msg: format!(" {} because {}", STOPPED_BY_BLOBDL, reason),
+            playlist_index,
+            category: ErrorCategory::TimedOut,
+        }
+    }
+
+    /// Parses a YtdlpError object from a ytdlp line which contains an error.
+    ///
+    /// Lines usually look like `ERROR: [youtube] dQw4w9WgXcQ: reason`, but the tag, the id or both can be missing
+    /// (`ERROR: [generic] reason`, `ERROR: Postprocessing: reason`). Whatever can't be found is left out, the message
+    /// is kept as yt-dlp wrote it
     pub fn from_error_output(error_line: &str) -> YtdlpError {
-        // yt-dlp error line format: ERROR: [...] video_id: reason
-        let mut section = error_line.split_whitespace();
-
-        // Skip ERROR:
-        section.next().unwrap();
-
-        let mut video_id;
-
-        //  for normal errors this should be [youtube]
-        let youtube = section.next().unwrap();
-
-        let is_normal_error = youtube == "[youtube]";
-        // todo find a decent way to do this
-        let mut strange_err_msg_beginning = "";
-
-        if is_normal_error {
-            // This is a usual error, so the video is in the next section
-            video_id = section.next().unwrap();
-            // Delete the trailing ':'
-            video_id = &video_id[..video_id.len() - 1];
-        } else {
-            // The video doesn't exist, this happens in errors such as NONEXISTENT_VIDEO (see lib.rs)
-            strange_err_msg_beginning = youtube;
-            video_id = "unavailable";
+        let line = parse_line(error_line, "ERROR:");
+        let error_msg = line.message.to_string();
+        let category = classifier::classify(&error_msg, &[]);
+
+        YtdlpError {
+            video_id: line.id.unwrap_or("unavailable").to_string(),
+            extractor: line.tag.map(str::to_string),
+            error_msg,
+            playlist_index: None,
+            category,
         }
+    }
+}
 
-        // Concatenate together the error message and restore whitespace
-        let error_msg = {
-            let mut tmp = String::new();
-            // I am ashamed
-            if !is_normal_error {
-                tmp += strange_err_msg_beginning;
-            }
+/// The parts of a line yt-dlp prints to report something: `PREFIX [tag] id: message`
+#[derive(Debug, PartialEq)]
+struct OutputLine<'a> {
+    /// The extractor (`youtube`, `youtube:tab`, `generic`, ...) or postprocessor (`Merger`, `ExtractAudio`, ...)
+    tag: Option<&'a str>,
+    id: Option<&'a str>,
+    /// Starts with the space which came before it
+    message: &'a str,
+}
 
-            for word in section {
-                tmp = tmp + " " + word;
+/// Splits a line into its parts, `prefix` (such as `ERROR:`) and anything before it are skipped
+fn parse_line<'a>(line: &'a str, prefix: &str) -> OutputLine<'a> {
+    let mut rest = line.find(prefix).map(|position| &line[position + prefix.len()..]).unwrap_or(line);
+
+    let mut tag = None;
+    if let Some(tagged) = rest.trim_start().strip_prefix('[') {
+        if let Some((name, after)) = tagged.split_once(']') {
+            tag = Some(name);
+            rest = after;
+        }
+    }
+
+    // Only extractors put ids in their messages, they never contain whitespace
+    let mut id = None;
+    if tag.is_some() {
+        if let Some((candidate, after)) = rest.trim_start().split_once(':') {
+            if !candidate.is_empty() && !candidate.contains(char::is_whitespace) && after.starts_with(' ') {
+                id = Some(candidate);
+                rest = after;
             }
-            tmp
-        };
+        }
+    }
+
+    OutputLine { tag, id, message: rest }
+}
+
+/// Returns what a `WARNING:` line says, such as ffmpeg not being installed or youtube slowing downloads down.
+///
+/// The tag and id are kept, since they tell which video the warning is about
+pub(crate) fn warning_from_output(line: &str) -> Option<String> {
+    if !line.contains("WARNING:") {
+        return None;
+    }
+    let parsed = parse_line(line, "WARNING:");
+    let mut warning = String::new();
+    if let Some(tag) = parsed.tag {
+        warning += &format!("[{}] ", tag);
+    }
+    if let Some(id) = parsed.id {
+        warning += &format!("{}: ", id);
+    }
+    warning += parsed.message.trim();
+    Some(warning)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_lines() {
+        let error = YtdlpError::from_error_output("ERROR: [youtube] dQw4w9WgXcQ: Private video. Sign in if you've been granted access");
+        assert_eq!(error.video_id(), "dQw4w9WgXcQ");
+        assert_eq!(error.extractor.as_deref(), Some("youtube"));
+        assert_eq!(error.error_msg(), " Private video. Sign in if you've been granted access");
+
+        let error = YtdlpError::from_error_output("ERROR: [youtube:tab] PLxyz: YouTube said:  The playlist does not exist.");
+        assert_eq!(error.video_id(), "PLxyz");
+        assert_eq!(error.extractor.as_deref(), Some("youtube:tab"));
+        assert_eq!(error.error_msg(), " YouTube said:  The playlist does not exist.");
+
+        let error = YtdlpError::from_error_output("ERROR: [generic] 'nope' is not a valid URL. Set --default-search");
+        assert_eq!(error.video_id(), "unavailable");
+        assert_eq!(error.extractor.as_deref(), Some("generic"));
+        assert_eq!(error.error_msg(), " 'nope' is not a valid URL. Set --default-search");
+
+        let error = YtdlpError::from_error_output("ERROR: Postprocessing: Error selecting an encoder for stream 0:1");
+        assert_eq!(error.video_id(), "unavailable");
+        assert_eq!(error.extractor, None);
+        assert_eq!(error.error_msg(), " Postprocessing: Error selecting an encoder for stream 0:1");
+        assert_eq!(error.category(), ErrorCategory::Postprocessing);
+
+        let error = YtdlpError::from_error_output("ERROR: [Merger] Could not merge: ffmpeg exited with code 1");
+        assert_eq!(error.video_id(), "unavailable");
+        assert_eq!(error.extractor.as_deref(), Some("Merger"));
+    }
+
+    #[test]
+    fn broken_error_lines() {
+        for line in ["ERROR:", "ERROR: ", "ERROR: [", "ERROR: [youtube]", "ERROR: [youtube] :", "ERROR: [youtube] abc:", "", "garbage"] {
+            let error = YtdlpError::from_error_output(line);
+            assert_eq!(error.video_id(), "unavailable", "{}", line);
+        }
+    }
 
-        YtdlpError { video_id: video_id.to_string(), error_msg }
+    #[test]
+    fn warning_lines() {
+        assert_eq!(warning_from_output("WARNING: ffmpeg not found. The downloaded format may not be the best available."),
+                   Some(String::from("ffmpeg not found. The downloaded format may not be the best available.")));
+        assert_eq!(warning_from_output("WARNING: [youtube] abc: Some formats are possibly damaged"),
+                   Some(String::from("[youtube] abc: Some formats are possibly damaged")));
+        assert_eq!(warning_from_output("[download] 50%"), None);
     }
 }
\ No newline at end of file
@@ -0,0 +1,256 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::{Deserialize, Serialize};
+
+pub use crate::error::ErrorCategory;
+
+/// Set once at startup when the user asks for JSON output
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Where events are written: the original stdout, since everything else printed to stdout is sent to stderr.
+///
+/// None means that stdout couldn't be redirected, events are then printed to stdout like everything else
+static OUTPUT: Mutex<Option<File>> = Mutex::new(None);
+
+/// What blob-dl prints with `--output-format json`: one Event per line on stdout, so that other programs can follow a download.
+///
+/// The `event` field says which one it is. Everything meant for people (status messages, yt-dlp's output, prompts)
+/// goes to stderr instead, without colors or spinners
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    RunStarted(RunStarted),
+    ItemResolved(ItemResolved),
+    Progress(Progress),
+    ItemFinished(ItemFinished),
+    ItemFailed(ItemFailed),
+    RunSummary(RunSummary),
+}
+
+/// blob-dl started downloading
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunStarted {
+    pub url: Option<String>,
+    /// The name of the job keeping track of the download, see `blob-dl resume`
+    pub job: String,
+    pub playlist: bool,
+}
+
+/// yt-dlp started downloading a video
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemResolved {
+    pub id: String,
+    pub title: Option<String>,
+    /// Only present when downloading a playlist
+    pub playlist_index: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressStatus {
+    Downloading,
+    /// A file (a video stream, an audio stream or a whole video) finished downloading, it may still have to be processed
+    Finished,
+    Error,
+}
+
+/// A progress update from yt-dlp, fields it didn't know are null
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub id: Option<String>,
+    pub playlist_index: Option<usize>,
+    pub status: ProgressStatus,
+    pub downloaded_bytes: Option<u64>,
+    /// The exact size if yt-dlp knows it, otherwise its estimate
+    pub total_bytes: Option<u64>,
+    /// Bytes per second
+    pub speed: Option<f64>,
+    /// Seconds
+    pub eta: Option<u64>,
+}
+
+/// A file is done and in its final location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemFinished {
+    pub id: String,
+    pub title: Option<String>,
+    pub playlist_index: Option<usize>,
+    pub path: PathBuf,
+    /// Bytes, None if the file couldn't be read
+    pub size: Option<u64>,
+}
+
+/// A video couldn't be downloaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemFailed {
+    /// `unavailable` when yt-dlp didn't say which video failed
+    pub id: String,
+    pub playlist_index: Option<usize>,
+    /// How blob-dl classified the error, with the names used by `retry_on` and `error_rules` in the config file (e.g. `Private`)
+    pub category: ErrorCategory,
+    /// A short description of the category
+    pub reason: String,
+    /// What the user can do about it
+    pub hint: String,
+    /// What yt-dlp said
+    pub message: String,
+}
+
+/// The run is over, the same summary which is shown to people at the end
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub url: String,
+    pub job: String,
+    /// The user stopped the run with Ctrl-C
+    pub interrupted: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Videos which were already in the download archive, or which an earlier run of the job downloaded
+    pub skipped: usize,
+    /// Size of the downloaded files
+    pub bytes: u64,
+    /// Measured in seconds
+    pub elapsed: f64,
+    pub warnings: Vec<String>,
+    /// The status blob-dl exits with if this was its only run, see ExitStatus
+    pub exit_code: u8,
+    /// Only present when SponsorBlock was used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sponsorblock: Option<SponsorBlockTotals>,
+}
+
+/// How much of the downloaded files SponsorBlock removed, or marked as chapters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SponsorBlockTotals {
+    /// `removed` or `marked`
+    pub action: String,
+    /// Measured in seconds, every file included
+    pub total: f64,
+    /// The files SponsorBlock found segments in
+    pub files: Vec<SponsorBlockFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SponsorBlockFile {
+    pub id: String,
+    pub title: Option<String>,
+    /// Measured in seconds
+    pub total: f64,
+    pub segments: Vec<SponsorBlockCut>,
+}
+
+/// A segment SponsorBlock removed or marked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SponsorBlockCut {
+    /// SponsorBlock's name for it (sponsor, intro, outro, selfpromo, ...)
+    pub category: String,
+    /// Measured in seconds
+    pub duration: f64,
+}
+
+/// Switches blob-dl to JSON output, this has to be done before anything is printed.
+///
+/// Colors are turned off, and stdout is redirected to stderr so that only events are left on stdout.
+/// This is only done on unix, the parser refuses --output-format json everywhere else
+pub(crate) fn enable() {
+    colored::control::set_override(false);
+    ENABLED.store(true, Ordering::SeqCst);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::FromRawFd;
+
+        let _ = io::stdout().flush();
+        // SAFETY: dup() and dup2() only duplicate file descriptors, the new one is owned by the File and nothing else
+        unsafe {
+            let events = libc::dup(libc::STDOUT_FILENO);
+            if events >= 0 {
+                libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO);
+                if let Ok(mut output) = OUTPUT.lock() {
+                    *output = Some(File::from_raw_fd(events));
+                }
+            }
+        }
+    }
+}
+
+/// Whether events are printed instead of the usual decorations (progress bars, spinners, the summary)
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Prints `event` on a line of its own, if JSON output is enabled
+pub(crate) fn emit(event: Event) {
+    if !enabled() {
+        return;
+    }
+    let Ok(line) = serde_json::to_string(&event) else { return };
+
+    let mut output = match OUTPUT.lock() {
+        Ok(output) => output,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // Whoever is reading the events may have gone away, which doesn't stop the download
+    let _ = match output.as_mut() {
+        Some(file) => writeln!(file, "{}", line).and_then(|_| file.flush()),
+        None => writeln!(io::stdout(), "{}", line).and_then(|_| io::stdout().flush()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_failed_shape() {
+        let event = Event::ItemFailed(ItemFailed {
+            id: "dQw4w9WgXcQ".to_string(),
+            playlist_index: Some(3),
+            category: ErrorCategory::Private,
+            reason: "private video".to_string(),
+            hint: "Only the accounts the video was shared with can download it".to_string(),
+            message: "ERROR: [youtube] dQw4w9WgXcQ: Private video".to_string(),
+        });
+
+        let line = serde_json::to_string(&event).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&line).unwrap(), serde_json::json!({
+            "event": "item_failed",
+            "id": "dQw4w9WgXcQ",
+            "playlist_index": 3,
+            "category": "Private",
+            "reason": "private video",
+            "hint": "Only the accounts the video was shared with can download it",
+            "message": "ERROR: [youtube] dQw4w9WgXcQ: Private video",
+        }));
+        assert_eq!(serde_json::from_str::<Event>(&line).unwrap(), event);
+    }
+
+    #[test]
+    fn progress_shape() {
+        let event = Event::Progress(Progress {
+            id: Some("dQw4w9WgXcQ".to_string()),
+            playlist_index: None,
+            status: ProgressStatus::Downloading,
+            downloaded_bytes: Some(1024),
+            total_bytes: None,
+            speed: Some(512.0),
+            eta: Some(2),
+        });
+
+        let line = serde_json::to_string(&event).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&line).unwrap(), serde_json::json!({
+            "event": "progress",
+            "id": "dQw4w9WgXcQ",
+            "playlist_index": null,
+            "status": "downloading",
+            "downloaded_bytes": 1024,
+            "total_bytes": null,
+            "speed": 512.0,
+            "eta": 2,
+        }));
+        assert_eq!(serde_json::from_str::<Event>(&line).unwrap(), event);
+    }
+}
@@ -0,0 +1,323 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::assembling::youtube::config::DownloadConfig;
+use crate::dispatcher;
+use crate::error::{BlobResult, BlobdlError};
+use crate::job::{EntryStatus, Job};
+use crate::parser::HistoryFilter;
+use crate::run::{self, DownloadedFile};
+
+/// Every run appends one line (a HistoryRecord) to this file, in blob-dl's data directory
+const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+fn history_path() -> BlobResult<PathBuf> {
+    dispatcher::get_data_dir().map(|dir| dir.join(HISTORY_FILE_NAME))
+}
+
+/// How a run went as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RunStatus {
+    /// Every video was downloaded (or there was nothing to download)
+    Success,
+    /// Some videos were downloaded, some failed
+    Partial,
+    /// Nothing was downloaded
+    Failed,
+}
+
+impl RunStatus {
+    fn name(&self) -> &'static str {
+        match self {
+            RunStatus::Success => "success",
+            RunStatus::Partial => "partial",
+            RunStatus::Failed => "failed",
+        }
+    }
+}
+
+/// A file which was downloaded during a run
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryFile {
+    id: String,
+    title: Option<String>,
+    path: PathBuf,
+    /// Measured in bytes
+    size: Option<u64>,
+    /// Measured in seconds
+    duration: Option<f64>,
+}
+
+/// A video which couldn't be downloaded
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryFailure {
+    id: String,
+    title: Option<String>,
+    error: Option<String>,
+}
+
+/// What blob-dl remembers about a run
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryRecord {
+    /// Records are numbered starting from 1
+    id: usize,
+    /// Unix timestamp of when the run started
+    started: u64,
+    url: String,
+    /// The job the run belongs to, see job.rs
+    job: String,
+    /// Every yt-dlp command blob-dl ran (retries included), program included
+    #[serde(default)]
+    commands: Vec<Vec<String>>,
+    /// Records saved by earlier versions of blob-dl only have the command it generated
+    #[serde(default, skip_serializing)]
+    command: Vec<String>,
+    download_config: DownloadConfig,
+    files: Vec<HistoryFile>,
+    failed: Vec<HistoryFailure>,
+    status: RunStatus,
+    /// Measured in seconds
+    elapsed: f64,
+}
+
+/// The program and arguments of a command, as they are stored in the history
+pub(crate) fn command_line(command: &Command) -> Vec<String> {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Appends a record of the run which has just finished to the history file.
+///
+/// `commands` are the yt-dlp commands the run started, `downloaded` the files it downloaded, failures are read from `job`
+pub(crate) fn record_run(commands: Vec<Vec<String>>, job: &Job, downloaded: &[DownloadedFile], started: SystemTime) -> BlobResult<()> {
+    let path = history_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let files: Vec<HistoryFile> = downloaded.iter()
+        .map(|file| HistoryFile {
+            id: file.id.clone(),
+            title: file.title.clone(),
+            path: file.filepath.clone(),
+            size: fs::metadata(&file.filepath).ok().map(|metadata| metadata.len()),
+            duration: file.duration,
+        })
+        .collect();
+    let failed: Vec<HistoryFailure> = job.state.entries.iter()
+        .filter(|entry| entry.status == EntryStatus::Failed)
+        .map(|entry| HistoryFailure { id: entry.id.clone(), title: entry.title.clone(), error: entry.error.clone() })
+        .collect();
+
+    let status = match (files.is_empty(), failed.is_empty()) {
+        (_, true) => RunStatus::Success,
+        (false, false) => RunStatus::Partial,
+        (true, false) => RunStatus::Failed,
+    };
+
+    let record = HistoryRecord {
+        id: read_history()?.len() + 1,
+        started: started.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        url: job.state.download_config.url.clone().unwrap_or_default(),
+        job: job.state.name.clone(),
+        commands,
+        command: vec![],
+        download_config: job.state.download_config.clone(),
+        files,
+        failed,
+        status,
+        elapsed: started.elapsed().unwrap_or(Duration::ZERO).as_secs_f64(),
+    };
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(&record)?)?;
+    Ok(())
+}
+
+/// Lines which can't be parsed are skipped, a missing file means that nothing was downloaded yet
+fn read_history() -> BlobResult<Vec<HistoryRecord>> {
+    match fs::read_to_string(history_path()?) {
+        Ok(contents) => Ok(contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// `blob-dl history`: lists the runs which match `filter`
+pub(crate) fn list(filter: &HistoryFilter) -> BlobResult<()> {
+    let records: Vec<HistoryRecord> = read_history()?.into_iter()
+        .filter(|record| matches(record, filter))
+        .collect();
+
+    if records.is_empty() {
+        println!("{} No downloads match", "[blob-dl]".purple());
+        return Ok(());
+    }
+
+    for record in records {
+        let status = match record.status {
+            RunStatus::Success => record.status.name().green(),
+            RunStatus::Partial => record.status.name().yellow(),
+            RunStatus::Failed => record.status.name().red(),
+        };
+        let size: u64 = record.files.iter().filter_map(|file| file.size).sum();
+        println!("{:>4}  {}  {:<7}  {}  ({} file(s), {}, {} failed)",
+                 record.id,
+                 format_date(record.started),
+                 status,
+                 record.url,
+                 record.files.len(),
+                 run::format_bytes(size),
+                 record.failed.len());
+    }
+    println!("{} See what a run did with `blob-dl history show ID`", "[blob-dl]".purple());
+    Ok(())
+}
+
+/// `blob-dl history show <id>`: everything that is known about a run, including the commands blob-dl ran
+pub(crate) fn show(id: usize) -> BlobResult<()> {
+    let record = read_history()?.into_iter()
+        .find(|record| record.id == id)
+        .ok_or(BlobdlError::HistoryRecordNotFound)?;
+
+    println!("{} {}", "Run".bold().cyan(), record.id);
+    println!("   url:      {}", record.url);
+    println!("   started:  {}", format_date(record.started));
+    println!("   took:     {:.1}s", record.elapsed);
+    println!("   outcome:  {}", record.status.name());
+    println!("   job:      {}", record.job);
+
+    if !record.files.is_empty() {
+        println!("{}", "Downloaded files:".bold().cyan());
+        for file in &record.files {
+            println!("   {} [{}] {} ({})",
+                     file.title.as_deref().unwrap_or("?"),
+                     file.id,
+                     file.path.display(),
+                     file.size.map(run::format_bytes).unwrap_or_else(|| "missing".to_string()));
+        }
+    }
+    if !record.failed.is_empty() {
+        println!("{}", "Failed videos:".bold().cyan());
+        for failure in &record.failed {
+            println!("   {}: {}", failure.id, failure.error.as_deref().unwrap_or("unknown error").red());
+        }
+    }
+
+    if !record.commands.is_empty() {
+        println!("{}", "Commands run by blob-dl:".bold().cyan());
+        for command in &record.commands {
+            println!("{}", command.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" "));
+        }
+    } else if !record.command.is_empty() {
+        println!("{}", "Command generated by blob-dl:".bold().cyan());
+        println!("{}", record.command.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" "));
+    }
+    Ok(())
+}
+
+fn matches(record: &HistoryRecord, filter: &HistoryFilter) -> bool {
+    // Dates are formatted as YYYY-MM-DD, so they can be compared as strings
+    let date = format_date(record.started);
+    let day = &date[..10];
+
+    filter.since.as_ref().is_none_or(|since| day >= since.as_str())
+        && filter.until.as_ref().is_none_or(|until| day <= until.as_str())
+        && filter.url.as_ref().is_none_or(|url| record.url.contains(url.as_str()))
+        && filter.status.as_ref().is_none_or(|status| record.status.name() == status)
+}
+
+/// Wraps arguments in single quotes when a shell would split or expand them
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM` (UTC)
+pub(crate) fn format_date(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+
+    // Converts days since 1970-01-01 to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, seconds / 3600, (seconds % 3600) / 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(started: u64, url: &str, status: RunStatus) -> HistoryRecord {
+        HistoryRecord {
+            id: 1,
+            started,
+            url: url.to_string(),
+            job: "job".to_string(),
+            commands: vec![],
+            command: vec![],
+            download_config: DownloadConfig::empty(),
+            files: vec![],
+            failed: vec![],
+            status,
+            elapsed: 0.0,
+        }
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(format_date(0), "1970-01-01 00:00");
+        assert_eq!(format_date(1704067199), "2023-12-31 23:59");
+        // Leap days
+        assert_eq!(format_date(1709164800), "2024-02-29 00:00");
+        assert_eq!(format_date(951825600), "2000-02-29 12:00");
+        assert_eq!(format_date(1709251200 + 90), "2024-03-01 00:01");
+    }
+
+    #[test]
+    fn filters() {
+        // 2024-02-29 12:00
+        let record = record(1709208000, "https://www.youtube.com/playlist?list=PL123", RunStatus::Partial);
+
+        assert!(matches(&record, &HistoryFilter::default()));
+        // The bounds are inclusive
+        assert!(matches(&record, &HistoryFilter { since: Some("2024-02-29".to_string()), until: Some("2024-02-29".to_string()), ..HistoryFilter::default() }));
+        assert!(!matches(&record, &HistoryFilter { since: Some("2024-03-01".to_string()), ..HistoryFilter::default() }));
+        assert!(!matches(&record, &HistoryFilter { until: Some("2024-02-28".to_string()), ..HistoryFilter::default() }));
+
+        assert!(matches(&record, &HistoryFilter { url: Some("list=PL123".to_string()), ..HistoryFilter::default() }));
+        assert!(!matches(&record, &HistoryFilter { url: Some("watch?v=".to_string()), ..HistoryFilter::default() }));
+
+        assert!(matches(&record, &HistoryFilter { status: Some("partial".to_string()), ..HistoryFilter::default() }));
+        assert!(!matches(&record, &HistoryFilter { status: Some("success".to_string()), ..HistoryFilter::default() }));
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(shell_quote("yt-dlp"), "yt-dlp");
+        assert_eq!(shell_quote("https://www.youtube.com/watch?v=abc"), "'https://www.youtube.com/watch?v=abc'");
+        assert_eq!(shell_quote("%(title)s.%(ext)s"), "'%(title)s.%(ext)s'");
+        assert_eq!(shell_quote("My Music"), "'My Music'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::archive::Archive;
+use crate::assembling::youtube::{config::DownloadConfig, PlaylistItem};
+use crate::dispatcher;
+use crate::error::{BlobResult, BlobdlError, ErrorCategory, YtdlpError};
+use crate::run::DownloadedFile;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum EntryStatus {
+    /// Nothing happened to it yet, or the run was interrupted before getting to it
+    Pending,
+    Downloaded,
+    Failed,
+}
+
+/// A video which is part of a job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct JobEntry {
+    pub(crate) id: String,
+    pub(crate) title: Option<String>,
+    pub(crate) playlist_index: Option<usize>,
+    pub(crate) status: EntryStatus,
+    /// The last error yt-dlp ran into while downloading this video
+    pub(crate) error: Option<String>,
+    #[serde(default)]
+    pub(crate) category: Option<ErrorCategory>,
+    pub(crate) file: Option<PathBuf>,
+}
+
+/// Everything needed to pick up a download where it was left
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct JobState {
+    pub(crate) name: String,
+    /// Unix timestamp
+    pub(crate) started: u64,
+    pub(crate) download_config: DownloadConfig,
+    pub(crate) entries: Vec<JobEntry>,
+}
+
+/// A download and the file its state is saved to.
+///
+/// Every video is recorded in the job as soon as it is done, so even if blob-dl is killed the videos which were
+/// downloaded aren't downloaded again by `blob-dl resume`. The file is deleted once everything was downloaded
+#[derive(Debug)]
+pub(crate) struct Job {
+    path: PathBuf,
+    pub(crate) state: JobState,
+}
+
+/// Jobs which weren't finished are deleted after this many seconds (30 days), nobody is going to resume them anymore
+const JOB_MAX_AGE: u64 = 30 * 24 * 60 * 60;
+
+/// Jobs are saved in blob-dl's data directory
+fn jobs_dir() -> BlobResult<PathBuf> {
+    dispatcher::get_data_dir().map(|dir| dir.join("jobs"))
+}
+
+impl Job {
+    /// Starts keeping track of a new download, jobs without a name are named after the moment they started.
+    ///
+    /// An existing job called `name` is only replaced if `replace` is true
+    pub(crate) fn create(name: Option<&str>, download_config: DownloadConfig, replace: bool) -> BlobResult<Job> {
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let dir = jobs_dir()?;
+        fs::create_dir_all(&dir)?;
+        prune(&dir, started);
+
+        let (name, path) = match name {
+            Some(name) => {
+                let path = job_path(&dir, name)?;
+                if path.exists() && !replace {
+                    return Err(BlobdlError::JobAlreadyExists);
+                }
+                (name.to_string(), path)
+            }
+            None => {
+                // More than one download can start in the same second
+                let mut name = format!("job-{}", started);
+                let mut attempt = 1;
+                while dir.join(format!("{}.json", name)).exists() {
+                    attempt += 1;
+                    name = format!("job-{}-{}", started, attempt);
+                }
+                let path = dir.join(format!("{}.json", name));
+                (name, path)
+            }
+        };
+
+        let job = Job {
+            path,
+            state: JobState { name, started, download_config, entries: vec![] },
+        };
+        job.save()?;
+        Ok(job)
+    }
+
+    /// Loads the job called `name`, or the latest one which still has videos left to download
+    pub(crate) fn load(name: Option<&str>) -> BlobResult<Job> {
+        let dir = jobs_dir()?;
+
+        if let Some(name) = name {
+            let path = job_path(&dir, name)?;
+            return match fs::read_to_string(&path) {
+                Ok(contents) => Ok(Job { path, state: serde_json::from_str(&contents)? }),
+                Err(_) => Err(BlobdlError::JobNotFound),
+            };
+        }
+
+        let mut latest: Option<Job> = None;
+        for file in fs::read_dir(&dir).map_err(|_| BlobdlError::JobNotFound)?.flatten() {
+            let path = file.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let state: JobState = match fs::read_to_string(&path).ok().and_then(|contents| serde_json::from_str(&contents).ok()) {
+                Some(state) => state,
+                None => continue,
+            };
+            let job = Job { path, state };
+            if !job.is_complete() && latest.as_ref().is_none_or(|latest| latest.state.started <= job.state.started) {
+                latest = Some(job);
+            }
+        }
+        latest.ok_or(BlobdlError::JobNotFound)
+    }
+
+    /// Deletes the job called `name` if there is one, along with its download archive if it is kept in the jobs directory
+    pub(crate) fn delete(name: &str) -> BlobResult<()> {
+        let job = match Job::load(Some(name)) {
+            Ok(job) => job,
+            Err(BlobdlError::JobNotFound) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if let (Some(archive), Ok(dir)) = (job.archive_path(), jobs_dir()) {
+            if archive.starts_with(&dir) {
+                let _ = fs::remove_file(archive);
+            }
+        }
+        fs::remove_file(&job.path)?;
+        Ok(())
+    }
+
+    pub(crate) fn save(&self) -> BlobResult<()> {
+        fs::write(&self.path, serde_json::to_string_pretty(&self.state)?)?;
+        Ok(())
+    }
+
+    /// A job is complete once the entries are known and they have all been downloaded
+    pub(crate) fn is_complete(&self) -> bool {
+        !self.state.entries.is_empty() && self.state.entries.iter().all(|entry| entry.status == EntryStatus::Downloaded)
+    }
+
+    /// Deletes the job's file if everything was downloaded, there is nothing left to resume or retry
+    pub(crate) fn remove_if_complete(&self) {
+        if self.is_complete() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    /// The videos an earlier run of the job downloaded
+    pub(crate) fn downloaded_ids(&self) -> HashSet<String> {
+        self.state.entries.iter()
+            .filter(|entry| entry.status == EntryStatus::Downloaded)
+            .map(|entry| entry.id.clone())
+            .collect()
+    }
+
+    pub(crate) fn failed_entries(&self) -> Vec<JobEntry> {
+        self.state.entries.iter().filter(|entry| entry.status == EntryStatus::Failed).cloned().collect()
+    }
+
+    /// Adds the videos which aren't part of the job yet, the ones which already are keep their status
+    pub(crate) fn add_entries(&mut self, items: &[PlaylistItem]) -> BlobResult<()> {
+        for item in items {
+            if !self.state.entries.iter().any(|entry| entry.id == item.id) {
+                self.state.entries.push(JobEntry {
+                    id: item.id.clone(),
+                    title: item.title.clone(),
+                    playlist_index: Some(item.playlist_index),
+                    status: EntryStatus::Pending,
+                    error: None,
+                    category: None,
+                    file: None,
+                });
+            }
+        }
+        self.save()
+    }
+
+    /// Updates every entry with what happened during a run and saves the job.
+    ///
+    /// `errors` are in the order they occurred, a video which was downloaded in the end isn't marked as failed
+    pub(crate) fn record(&mut self, downloaded: &[DownloadedFile], errors: &[YtdlpError]) -> BlobResult<()> {
+        let archive = match self.archive_path() {
+            Some(path) => Some(Archive::read(&path)?),
+            None => None,
+        };
+
+        // Videos can show up for the first time here when the playlist wasn't listed in advance
+        let new_videos = downloaded.iter()
+            .map(|file| (file.id.as_str(), file.title.clone(), file.playlist_index))
+            .chain(errors.iter()
+                .filter(|error| error.video_id() != "unavailable")
+                .map(|error| (error.video_id().as_str(), None, error.playlist_index())));
+        for (id, title, playlist_index) in new_videos {
+            if !self.state.entries.iter().any(|entry| entry.id == id) {
+                self.state.entries.push(JobEntry {
+                    id: id.to_string(),
+                    title,
+                    playlist_index,
+                    status: EntryStatus::Pending,
+                    error: None,
+                    category: None,
+                    file: None,
+                });
+            }
+        }
+
+        for entry in &mut self.state.entries {
+            // Errors without a video id can still be matched by playlist index
+            let error = errors.iter().rev().find(|error| match error.video_id().as_str() {
+                "unavailable" => entry.playlist_index.is_some() && error.playlist_index() == entry.playlist_index,
+                id => id == entry.id,
+            });
+
+            if let Some(file) = downloaded.iter().find(|file| file.id == entry.id) {
+                entry.status = EntryStatus::Downloaded;
+                entry.file = Some(file.filepath.clone());
+                entry.title = file.title.clone().or(entry.title.take());
+                entry.error = None;
+                entry.category = None;
+            } else if archive.as_ref().is_some_and(|archive| archive.contains(&entry.id)) {
+                entry.status = EntryStatus::Downloaded;
+                entry.error = None;
+                entry.category = None;
+            } else if let Some(error) = error {
+                entry.status = EntryStatus::Failed;
+                entry.error = Some(error.error_msg().trim().to_string());
+                entry.category = Some(error.category());
+            }
+        }
+        self.save()
+    }
+
+    fn archive_path(&self) -> Option<PathBuf> {
+        self.state.download_config.archive_path().ok().flatten()
+    }
+
+    /// Tells the user how the job went and how to continue it
+    pub(crate) fn print_status(&self) {
+        let count = |status: EntryStatus| self.state.entries.iter().filter(|entry| entry.status == status).count();
+        let failed = count(EntryStatus::Failed);
+        let pending = count(EntryStatus::Pending);
+
+        if failed > 0 {
+            println!("{} {} video(s) of job {} failed, try them again later with `blob-dl retry-failed {}`",
+                     "[blob-dl]".purple(), failed, self.state.name, self.state.name);
+        }
+        if pending > 0 {
+            println!("{} {} video(s) of job {} weren't downloaded yet, continue with `blob-dl resume {}`",
+                     "[blob-dl]".purple(), pending, self.state.name, self.state.name);
+        }
+    }
+}
+
+/// Where the job called `name` is saved, names which would put the file outside of `dir` are refused
+fn job_path(dir: &Path, name: &str) -> BlobResult<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        return Err(BlobdlError::InvalidJobName);
+    }
+    Ok(dir.join(format!("{}.json", name)))
+}
+
+/// Deletes the jobs in `dir` which were started more than JOB_MAX_AGE seconds before `now`
+fn prune(dir: &Path, now: u64) {
+    let Ok(files) = fs::read_dir(dir) else { return };
+    for path in files.flatten().map(|file| file.path()) {
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let state: Option<JobState> = fs::read_to_string(&path).ok().and_then(|contents| serde_json::from_str(&contents).ok());
+        if state.is_some_and(|state| now.saturating_sub(state.started) > JOB_MAX_AGE) {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_names() {
+        let dir = Path::new("jobs");
+        assert_eq!(job_path(dir, "my-playlist_2").unwrap(), dir.join("my-playlist_2.json"));
+        assert_eq!(job_path(dir, "sync-home-music").unwrap(), dir.join("sync-home-music.json"));
+
+        for name in ["", "a/b", "/etc/passwd", "a\\b", "..", "../jobs", "a..b"] {
+            assert!(matches!(job_path(dir, name), Err(BlobdlError::InvalidJobName)), "{:?} was accepted", name);
+        }
+    }
+}
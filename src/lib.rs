@@ -4,6 +4,18 @@ mod analyzer;
 mod dispatcher;
 mod run;
 mod error;
+mod tracklist;
+mod m3u;
+mod archive;
+mod sync;
+mod job;
+mod history;
+mod watch;
+mod queue;
+mod classifier;
+mod ytdlp;
+
+pub mod events;
 
 pub mod app;
 
@@ -41,10 +53,16 @@ pub mod ui_prompts {
 
     pub const ERROR_RETRY_PROMPT: &str = "The following videos weren't downloaded but retrying might help, choose which videos to re-download [space bar to select]";
 
+    pub const REMEDIATION_PROMPT: &str = "What should be done about";
+
+    pub const COOKIES_FILE_PROMPT: &str = "Path to a cookies file (Netscape format, it can be exported from your browser)";
+
     pub const UNRECOVERABLE_ERROR_PROMPT: &str = "The following videos could not be downloaded due to unrecoverable errors";
 
     pub const DEBUG_REPORT_PROMPT: &str = "By default new errors are flagged as unrecoverable, if any recoverable errors are flagged incorrectly please report them to the github page";
 
+    pub const REMOVE_FRAGMENTS_PROMPT: &str = "The download was interrupted, delete the partially downloaded files (.part, .ytdl)?";
+
     pub const SELECT_ALL: &str = "Select all\n";
     pub const SELECT_NOTHING: &str = "Don't re-download anything\n";
     
@@ -54,38 +72,6 @@ pub mod ui_prompts {
     pub const COMMAND_NOT_SPAWNED: &str = "An instance of ytdlp (used to check which version of the program you have installed) could not be spawned";
 }
 
-// Youtube's error messages
-// THESE SHOULD NOT BE MODIFIED, they are supposed to match exactly youtube's error messages
-mod youtube_error_message {
-    pub const PRIVATE_VIDEO: &str = " Private video. Sign in if you've been granted access to this video";
-
-    pub const NONEXISTENT_PLAYLIST: &str = " YouTube said: The playlist does not exist.";
-
-    pub const HOMEPAGE_REDIRECT: &str = " The channel/playlist does not exist and the URL redirected to youtube.com home page";
-
-    pub const NETWORK_FAIL: &str = " Unable to download API page: <urlopen error [Errno -3] Temporary failure in name resolution> (caused by URLError(gaierror(-3, 'Temporary failure in name resolution')))";
-
-    pub const VIOLENT_VIDEO: &str = " This video has been removed for violating YouTube's policy on violent or graphic content";
-
-    pub const REMOVED_VIDEO: &str = " Video unavailable. This video has been removed by the uploader";
-
-    pub const VIDEO_NOT_FOUND: &str = " not found, unable to continue";
-
-    pub const YTDLP_GAVE_UP: &str = " error: HTTP Error 403: Forbidden. Giving up after 10 retries";
-
-    pub const NO_API_PAGE: &str = " Unable to download API page: HTTP Error 404: Not Found (caused by <HTTPError 404: 'Not Found'>); please report this issue on https://github.com/yt-dlp/yt-dlp/issues?q= , filling out the appropriate issue template. Confirm you are on the latest version using yt-dlp -U";
-
-    pub const ENCODER_STREAM_ERROR: &str = " Postprocessing: Error selecting an encoder for stream 0:1";
-
-    pub const NONEXISTENT_VIDEO: &str = "Incomplete data received";
-    
-    pub const NONEXISTENT_FORMAT: &str = "Requested format is not available";
-
-    // All copyright error messages begin with this
-    pub const VIDEO_UNAVAILABLE: &str = " Video unavailable";
-}
-
-
 // blob-dl custom error messages
 pub mod blobdl_error_message {
     pub const BROKEN_URL_ERR: &str = "The URL you provided wasn't recognized, try using a regular youtube URL";
@@ -129,5 +115,22 @@ pub mod blobdl_error_message {
     
     pub const FFMPEG_NOT_AVAILABLE_CONFIG_WARNING: &str = "You are using a config file which tells blob-dl to convert the files you download to a specific format. Doing this requires ffmpeg, which is not installed on your system";
     
+    pub const TRACKLIST_SPLIT_ERR: &str = "ffmpeg couldn't split a downloaded file into the tracks listed in its description, the original file was kept";
+
+    pub const INVALID_CLIP_SECTION_ERR: &str = "A time range passed with --clip couldn't be understood, ranges look like START-END (for example 1:30-4:00, 90-240 or 1:02:00-end)";
+
+    pub const DATA_DIR_NOT_FOUND_ERR: &str = "No valid home directory path could be retrieved from the operating system. (this problem has to do with the location of blob-dl's data, such as the global download archive)";
+    pub const JOB_NOT_FOUND_ERR: &str = "There is no job to continue: pass the name of an existing job, or start a new download";
+    pub const HISTORY_RECORD_NOT_FOUND_ERR: &str = "There is no download with this id in the history, see `blob-dl history` for the list";
+    pub const YTDLP_NOT_STARTED_ERR: &str = "yt-dlp couldn't be started:";
+    pub const UNKNOWN_REPORT_FORMAT_ERR: &str = "Reports can only be saved as json or markdown, e.g. --report markdown report.md";
+    pub const INVALID_ERROR_RULE_ERR: &str = "A pattern in the error_rules of your config file isn't a valid regular expression:";
+    pub const QUEUE_ENTRY_NOT_FOUND_ERR: &str = "There is no entry with this id in the queue, see `blob-dl queue list` for the list";
+    pub const PROFILE_NOT_FOUND_ERR: &str = "There is no profile with this name, profiles are config files saved in the `profiles` folder of blob-dl's config directory";
+    pub const INVALID_CONFIG_FILE_ERR: &str = "The config file couldn't be read:";
+    pub const INVALID_JOB_NAME_ERR: &str = "Job names can't be empty or contain `/`, `\\` or `..`";
+    pub const JOB_ALREADY_EXISTS_ERR: &str = "There already is a job with this name: continue it with `blob-dl resume NAME`, or pass --replace-job to start it again from scratch";
+    pub const JSON_OUTPUT_UNSUPPORTED_ERR: &str = "--output-format json is only available on Linux and macOS, on other systems events would be mixed up with the rest of blob-dl's output";
+
     pub const JSON_GENERATION_ERR: &str = "yt-dlp didn't generate the json needed to parse formats for your video.\nThis most likely happened because the video you are trying to download is private/copyright claimed\nIf you are running the recommended version of yt-dlp please report this to the GitHub page.";
 }
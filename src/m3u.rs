@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::error::BlobResult;
+use crate::run::DownloadedFile;
+
+/// Every playlist directory remembers which files it contains here, so that the .m3u8 can be regenerated
+/// when a later run downloads the entries that failed
+const ENTRIES_FILE_NAME: &str = ".blob-dl-m3u.json";
+
+/// A file which belongs in a playlist's .m3u8
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PlaylistEntry {
+    /// Path relative to the playlist's directory
+    file_name: String,
+    title: String,
+    /// Measured in seconds
+    duration: Option<f64>,
+    /// None if yt-dlp didn't know where the video is in the playlist, these entries go at the end
+    playlist_index: Option<usize>,
+}
+
+/// Writes (or updates) an .m3u8 file in every directory files were downloaded to.
+///
+/// Entries are sorted in playlist order, files which were deleted since the last run are left out
+pub(crate) fn write_playlists(downloaded: &[DownloadedFile]) {
+    // Playlists are downloaded into a directory named after them
+    let mut directories: BTreeMap<PathBuf, Vec<&DownloadedFile>> = BTreeMap::new();
+    for file in downloaded {
+        if let Some(directory) = file.filepath.parent() {
+            directories.entry(directory.to_path_buf()).or_default().push(file);
+        }
+    }
+
+    for (directory, files) in directories {
+        match update_playlist(&directory, &files) {
+            Ok(path) => println!("{} Playlist file saved to {}", "[blob-dl]".purple(), path.display()),
+            Err(err) => err.report(),
+        }
+    }
+}
+
+/// Merges the new files with the ones downloaded by previous runs and regenerates the .m3u8
+///
+/// Returns the path to the .m3u8
+fn update_playlist(directory: &Path, files: &[&DownloadedFile]) -> BlobResult<PathBuf> {
+    let entries_path = directory.join(ENTRIES_FILE_NAME);
+
+    // Entries are identified by their file name
+    let mut entries: BTreeMap<String, PlaylistEntry> = match fs::read_to_string(&entries_path) {
+        Ok(contents) => serde_json::from_str::<Vec<PlaylistEntry>>(&contents)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| (entry.file_name.clone(), entry))
+            .collect(),
+        // This is the first time something is downloaded here
+        Err(_) => BTreeMap::new(),
+    };
+
+    for file in files {
+        let file_name = match file.filepath.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let previous_index = entries.get(&file_name).and_then(|entry| entry.playlist_index);
+
+        entries.insert(file_name.clone(), PlaylistEntry {
+            file_name,
+            title: file.title.clone().unwrap_or_else(|| file.id.clone()),
+            duration: file.duration,
+            // Retried videos don't always know their position, keep the one from the first run
+            playlist_index: file.playlist_index.or(previous_index),
+        });
+    }
+
+    // Skip files which have been deleted or moved
+    let mut entries: Vec<PlaylistEntry> = entries.into_values()
+        .filter(|entry| directory.join(&entry.file_name).exists())
+        .collect();
+    entries.sort_by(|a, b| {
+        match (a.playlist_index, b.playlist_index) {
+            (Some(a_index), Some(b_index)) => a_index.cmp(&b_index),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a.file_name.cmp(&b.file_name),
+        }
+    });
+
+    fs::write(&entries_path, serde_json::to_string_pretty(&entries)?)?;
+
+    let playlist_name = directory.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("playlist");
+    let m3u_path = directory.join(format!("{}.m3u8", playlist_name));
+
+    let mut m3u = fs::File::create(&m3u_path)?;
+    writeln!(m3u, "#EXTM3U")?;
+    for entry in entries {
+        // -1 is the conventional value for an unknown duration
+        let duration = entry.duration.map(|d| d.round() as i64).unwrap_or(-1);
+        writeln!(m3u, "#EXTINF:{},{}", duration, entry.title)?;
+        writeln!(m3u, "{}", entry.file_name)?;
+    }
+
+    Ok(m3u_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn downloaded(directory: &Path, name: &str, index: Option<usize>, duration: Option<f64>) -> DownloadedFile {
+        DownloadedFile {
+            id: name.to_string(),
+            title: Some(format!("Song {}", name)),
+            filepath: directory.join(format!("{}.mp3", name)),
+            duration,
+            playlist: Some("Album".to_string()),
+            playlist_index: index,
+            description: None,
+            sponsorblock_chapters: None,
+            format_id: None,
+            height: None,
+            vcodec: None,
+            acodec: None,
+        }
+    }
+
+    /// A fresh directory named Album, the files in `existing` are created inside it
+    fn album(test: &str, existing: &[&str]) -> PathBuf {
+        let directory = std::env::temp_dir()
+            .join(format!("blob-dl-m3u-test-{}-{}", test, std::process::id()))
+            .join("Album");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for name in existing {
+            fs::write(directory.join(format!("{}.mp3", name)), "").unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn playlist_order_and_extinf() {
+        let directory = album("order", &["a", "b", "c"]);
+        let files = [
+            downloaded(&directory, "c", None, Some(59.6)),
+            downloaded(&directory, "b", Some(1), None),
+            downloaded(&directory, "a", Some(2), Some(180.2)),
+        ];
+
+        let path = update_playlist(&directory, &files.iter().collect::<Vec<_>>()).unwrap();
+
+        assert_eq!(path, directory.join("Album.m3u8"));
+        // Paths are relative to the playlist, entries without an index go at the end
+        assert_eq!(fs::read_to_string(&path).unwrap(),
+                   "#EXTM3U\n#EXTINF:-1,Song b\nb.mp3\n#EXTINF:180,Song a\na.mp3\n#EXTINF:60,Song c\nc.mp3\n");
+        let _ = fs::remove_dir_all(directory.parent().unwrap());
+    }
+
+    #[test]
+    fn missing_files_are_skipped() {
+        // b failed, so there is no file for it
+        let directory = album("missing", &["a", "c"]);
+        let files = [
+            downloaded(&directory, "a", Some(1), Some(1.0)),
+            downloaded(&directory, "b", Some(2), Some(2.0)),
+            downloaded(&directory, "c", Some(3), Some(3.0)),
+        ];
+
+        let path = update_playlist(&directory, &files.iter().collect::<Vec<_>>()).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(),
+                   "#EXTM3U\n#EXTINF:1,Song a\na.mp3\n#EXTINF:3,Song c\nc.mp3\n");
+        let _ = fs::remove_dir_all(directory.parent().unwrap());
+    }
+
+    #[test]
+    fn later_runs_keep_earlier_entries() {
+        let directory = album("merge", &["a", "c"]);
+        let first = [
+            downloaded(&directory, "a", Some(1), Some(1.0)),
+            downloaded(&directory, "c", Some(3), Some(3.0)),
+        ];
+        update_playlist(&directory, &first.iter().collect::<Vec<_>>()).unwrap();
+
+        // The entry which failed the first time is retried without knowing its position
+        fs::write(directory.join("b.mp3"), "").unwrap();
+        let mut retried = downloaded(&directory, "b", None, Some(2.0));
+        let path = update_playlist(&directory, &[&retried]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(),
+                   "#EXTM3U\n#EXTINF:1,Song a\na.mp3\n#EXTINF:3,Song c\nc.mp3\n#EXTINF:2,Song b\nb.mp3\n");
+
+        // Once an index is known it is kept by later runs which don't know it
+        retried.playlist_index = Some(2);
+        update_playlist(&directory, &[&retried]).unwrap();
+        retried.playlist_index = None;
+        update_playlist(&directory, &[&retried]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(),
+                   "#EXTM3U\n#EXTINF:1,Song a\na.mp3\n#EXTINF:2,Song b\nb.mp3\n#EXTINF:3,Song c\nc.mp3\n");
+        let _ = fs::remove_dir_all(directory.parent().unwrap());
+    }
+}
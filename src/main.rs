@@ -1,5 +1,6 @@
+use std::process::ExitCode;
 use blob_dl::app;
 
-fn main() {
-    app::run();
-}
\ No newline at end of file
+fn main() -> ExitCode {
+    app::run().into()
+}
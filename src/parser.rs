@@ -7,6 +7,8 @@ use clap::{arg, value_parser, ArgAction, Command};
 
 use crate::ui_prompts::*;
 use crate::error::{BlobdlError, BlobResult};
+use crate::assembling::youtube::ClipSection;
+use crate::assembling::youtube::config::ArchivePreference;
 
 pub fn parse_config() -> BlobResult<CliConfig> {
     let matches = Command::new("blob-dl")
@@ -19,14 +21,16 @@ pub fn parse_config() -> BlobResult<CliConfig> {
                 .short('v')
                 .long("verbose")
                 .help("Show all the output produced by yt-dlp")
-                .action(ArgAction::SetTrue),
+                .action(ArgAction::SetTrue)
+                .global(true),
         )
         .arg(
             Arg::new("quiet")
                 .short('q')
                 .long("quiet")
                 .help("Silence all output except for the final error summary")
-                .action(ArgAction::SetTrue),
+                .action(ArgAction::SetTrue)
+                .global(true),
         )
         .arg(
             Arg::new("show-command")
@@ -56,16 +60,262 @@ pub fn parse_config() -> BlobResult<CliConfig> {
                 .required(false)
                 .value_parser(value_parser!(PathBuf)),
         )
+        .arg(
+            Arg::new("clip")
+                .help("Only download part of the video, e.g. 1:00-3:00 or 1:02:00-end. Can be used multiple times (requires ffmpeg)")
+                .long("clip")
+                .value_name("RANGE")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("jobs")
+                .help("How many videos of a playlist to download at the same time")
+                .long("jobs")
+                .short('j')
+                .value_name("N")
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("max-attempts")
+                .help("Retry failed downloads automatically instead of asking, downloading each video at most N times")
+                .long("max-attempts")
+                .value_name("N")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("timeout")
+                .help("Stop downloading a video if it takes more than SECONDS, it counts as failed")
+                .long("timeout")
+                .value_name("SECONDS")
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("stall-timeout")
+                .help("Stop downloading a video if it makes no progress for SECONDS, it counts as failed")
+                .long("stall-timeout")
+                .value_name("SECONDS")
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("download-archive")
+                .help("Skip the videos which have already been downloaded: `dir` keeps an archive in the output directory, `global` shares one between all downloads, or pass the path to an archive file")
+                .long("download-archive")
+                .value_name("WHERE"),
+        )
+        .arg(
+            Arg::new("report")
+                .help("Save a summary of the download to PATH, FORMAT can be json or markdown")
+                .long("report")
+                .num_args(2)
+                .value_names(["FORMAT", "PATH"])
+                .global(true),
+        )
+        .arg(
+            Arg::new("output-format")
+                .help("How blob-dl reports what it is doing: human, or json to print one event per line for other programs to read (Linux and macOS only)")
+                .long("output-format")
+                .value_name("FORMAT")
+                .value_parser(["human", "json"])
+                .default_value("human")
+                .global(true),
+        )
+        .arg(
+            Arg::new("job")
+                .help("Name the job which keeps track of this download, so that it can be resumed with `blob-dl resume NAME`")
+                .long("job")
+                .value_name("NAME"),
+        )
+        .arg(
+            Arg::new("replace-job")
+                .help("If a job called like the one passed to --job already exists, start it again from scratch instead of stopping")
+                .long("replace-job")
+                .requires("job")
+                .action(ArgAction::SetTrue),
+        )
         .arg(Arg::new("URL")
             .help("Link to the youtube video/playlist that you want to download")
         )
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            Command::new("archive")
+                .about("Show which videos a download archive contains, or remove some of them")
+                .arg(Arg::new("ARCHIVE")
+                    .help("The archive file or the directory it is in, the global archive is used by default")
+                    .value_parser(value_parser!(PathBuf)))
+                .arg(Arg::new("remove")
+                    .help("Remove a video from the archive so that it is downloaded again. Can be used multiple times")
+                    .long("remove")
+                    .value_name("VIDEO_ID")
+                    .action(ArgAction::Append))
+                .arg(Arg::new("prune")
+                    .help("Remove duplicated and invalid lines")
+                    .long("prune")
+                    .action(ArgAction::SetTrue))
+        )
+        .subcommand(
+            Command::new("sync")
+                .about("Make a directory a copy of a playlist: download new entries, renumber files whose position changed and deal with entries that were removed")
+                .arg(Arg::new("URL")
+                    .help("Link to the youtube playlist")
+                    .required(true))
+                .arg(Arg::new("DIR")
+                    .help("The directory which mirrors the playlist")
+                    .required(true)
+                    .value_parser(value_parser!(PathBuf)))
+                .arg(Arg::new("dry-run")
+                    .help("Only print what would be done")
+                    .long("dry-run")
+                    .action(ArgAction::SetTrue))
+                .arg(Arg::new("removed")
+                    .help("What to do with the files of entries that aren't in the playlist anymore")
+                    .long("removed")
+                    .value_parser(["keep", "move", "delete"])
+                    .default_value("keep"))
+                .arg(Arg::new("jobs")
+                    .help("How many videos to download at the same time")
+                    .long("jobs")
+                    .short('j')
+                    .value_name("N")
+                    .value_parser(value_parser!(u64).range(1..)))
+                .arg(Arg::new("use-config-file")
+                    .help("Use the preferences from the config file in blob-dl's default location instead of the ones saved by the last sync")
+                    .long("use-config")
+                    .short('c')
+                    .action(ArgAction::SetTrue))
+                .arg(arg!(
+                        -l --"locate-config-file" <FILEPATH> "Use the preferences from a config file instead of the ones saved by the last sync"
+                    )
+                    .required(false)
+                    .value_parser(value_parser!(PathBuf)))
+        )
+        .subcommand(
+            Command::new("resume")
+                .about("Continue an interrupted download with the preferences it was started with")
+                .arg(Arg::new("JOB")
+                    .help("The name of the job, by default the last one which didn't finish"))
+        )
+        .subcommand(
+            Command::new("history")
+                .about("List what blob-dl downloaded")
+                .args_conflicts_with_subcommands(true)
+                .arg(Arg::new("since")
+                    .help("Only show downloads from this day on (YYYY-MM-DD)")
+                    .long("since")
+                    .value_name("DATE")
+                    .value_parser(parse_date))
+                .arg(Arg::new("until")
+                    .help("Only show downloads up to this day (YYYY-MM-DD)")
+                    .long("until")
+                    .value_name("DATE")
+                    .value_parser(parse_date))
+                .arg(Arg::new("url")
+                    .help("Only show downloads whose url contains this text")
+                    .long("url")
+                    .value_name("TEXT"))
+                .arg(Arg::new("status")
+                    .help("Only show downloads which went this way")
+                    .long("status")
+                    .value_parser(["success", "partial", "failed"]))
+                .subcommand(
+                    Command::new("show")
+                        .about("Show what a download did and the command blob-dl generated for it")
+                        .arg(Arg::new("ID")
+                            .help("The number of the download, as listed by `blob-dl history`")
+                            .required(true)
+                            .value_parser(value_parser!(usize)))
+                )
+        )
+        .subcommand(
+            Command::new("retry-failed")
+                .about("Download again only the videos of a job which failed")
+                .arg(Arg::new("JOB")
+                    .help("The name of the job, by default the last one which didn't finish"))
+        )
+        .subcommand(
+            Command::new("watch")
+                .about("Check channels and playlists periodically and download their new videos")
+                .arg(Arg::new("URL")
+                    .help("Links to the channels/playlists to watch")
+                    .required(true)
+                    .num_args(1..))
+                .arg(Arg::new("interval")
+                    .help("How often the targets are checked, e.g. 90s, 30m, 6h or 1d (seconds if there is no unit)")
+                    .long("interval")
+                    .value_name("INTERVAL")
+                    .value_parser(parse_interval)
+                    .default_value("1h"))
+                .arg(Arg::new("once")
+                    .help("Check the targets once and exit, useful when blob-dl is run by cron")
+                    .long("once")
+                    .action(ArgAction::SetTrue))
+                .arg(Arg::new("jobs")
+                    .help("How many videos to download at the same time")
+                    .long("jobs")
+                    .short('j')
+                    .value_name("N")
+                    .value_parser(value_parser!(u64).range(1..)))
+                .arg(Arg::new("use-config-file")
+                    .help("Use the preferences from the config file in blob-dl's default location instead of asking questions")
+                    .long("use-config")
+                    .short('c')
+                    .action(ArgAction::SetTrue))
+                .arg(arg!(
+                        -l --"locate-config-file" <FILEPATH> "Use the preferences from a config file instead of asking questions"
+                    )
+                    .required(false)
+                    .value_parser(value_parser!(PathBuf)))
+        )
+        .subcommand(
+            Command::new("queue")
+                .about("Collect links to download them later, all at once")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Choose how to download a link and add it to the queue")
+                        .arg(Arg::new("URL")
+                            .help("Link to the youtube video/playlist")
+                            .required(true))
+                        .arg(Arg::new("profile")
+                            .help("Use the preferences saved as profiles/NAME.json in blob-dl's config directory instead of asking questions")
+                            .long("profile")
+                            .value_name("NAME")
+                            .conflicts_with_all(["use-config-file", "locate-config-file"]))
+                        .arg(Arg::new("use-config-file")
+                            .help("Use the preferences from the config file in blob-dl's default location instead of asking questions")
+                            .long("use-config")
+                            .short('c')
+                            .action(ArgAction::SetTrue))
+                        .arg(arg!(
+                                -l --"locate-config-file" <FILEPATH> "Use the preferences from a config file instead of asking questions"
+                            )
+                            .required(false)
+                            .value_parser(value_parser!(PathBuf)))
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("Show what is in the queue")
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove links from the queue")
+                        .arg(Arg::new("ID")
+                            .help("The numbers of the entries, as listed by `blob-dl queue list`")
+                            .required(true)
+                            .num_args(1..)
+                            .value_parser(value_parser!(usize)))
+                )
+                .subcommand(
+                    Command::new("run")
+                        .about("Download everything in the queue which wasn't downloaded yet, without asking anything")
+                )
+        )
         .get_matches();
     
     CliConfig::from(matches)
 }
 
 /// The 3 possible verbosity options for this program
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Verbosity {
     Verbose,
     Default,
@@ -82,6 +332,114 @@ pub enum ConfigFilePreferences {
     CustomConfig(PathBuf),
     /// Create a config file based on the user's answers and place it in blob-dl's default location
     GenerateConfig,
+    /// Use a config file saved as a profile in blob-dl's config directory, see `blob-dl queue add --profile`
+    Profile(String),
+}
+
+/// What `blob-dl sync` does with the files of videos which were removed from the playlist
+#[derive(Debug, Clone)]
+pub enum RemovedEntries {
+    /// Leave them where they are, but stop keeping track of them
+    Keep,
+    /// Move them to a `removed` directory
+    Move,
+    Delete,
+}
+
+/// How the end-of-run summary is exported
+#[derive(Debug)]
+pub enum ReportFormat {
+    Json,
+    Markdown,
+}
+
+/// What blob-dl prints to stdout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Colored messages, spinners and progress bars
+    Human,
+    /// One JSON object per line (see the events module), everything else goes to stderr
+    Json,
+}
+
+/// Where to save the end-of-run summary and how
+#[derive(Debug)]
+pub struct ReportPreference {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+/// Which downloads `blob-dl history` lists, None means any
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    /// YYYY-MM-DD
+    pub since: Option<String>,
+    /// YYYY-MM-DD
+    pub until: Option<String>,
+    /// Text the url has to contain
+    pub url: Option<String>,
+    /// success, partial or failed
+    pub status: Option<String>,
+}
+
+/// Things blob-dl can do other than downloading
+#[derive(Debug)]
+pub enum CliSubcommand {
+    /// Inspect or edit a download archive
+    Archive {
+        /// None means the global archive
+        location: Option<PathBuf>,
+        remove: Vec<String>,
+        prune: bool,
+    },
+    /// Mirror a playlist into a directory
+    Sync {
+        url: String,
+        directory: PathBuf,
+        dry_run: bool,
+        removed: RemovedEntries,
+        jobs: Option<usize>,
+        config_file_preference: ConfigFilePreferences,
+    },
+    /// Continue a job, None means the last one which didn't finish
+    Resume {
+        job: Option<String>,
+    },
+    /// List past downloads, or show one of them if `show` is Some
+    History {
+        filter: HistoryFilter,
+        show: Option<usize>,
+    },
+    /// Download the failed videos of a job again
+    RetryFailed {
+        job: Option<String>,
+    },
+    /// Download the new videos of some channels/playlists every `interval` seconds
+    Watch {
+        urls: Vec<String>,
+        interval: u64,
+        once: bool,
+        jobs: Option<usize>,
+        config_file_preference: ConfigFilePreferences,
+    },
+    /// Manage the download queue
+    Queue(QueueAction),
+}
+
+/// What `blob-dl queue` was asked to do
+#[derive(Debug)]
+pub enum QueueAction {
+    /// Resolve the preferences for a link and save it in the queue
+    Add {
+        url: String,
+        config_file_preference: ConfigFilePreferences,
+    },
+    List,
+    Remove {
+        ids: Vec<usize>,
+    },
+    /// Download the entries which weren't downloaded yet
+    Run,
 }
 
 /// Holds all the information that can be fetched as a command line argument
@@ -92,6 +450,26 @@ pub struct CliConfig {
     verbosity: Verbosity,
     // Whether to print to the console the final command which is the run by yt-dlp
     show_command: bool,
+    // Parts of the video which have to be downloaded, empty if the whole video is wanted
+    clip_sections: Vec<ClipSection>,
+    // How many videos of a playlist are downloaded at the same time, None if it wasn't specified
+    jobs: Option<usize>,
+    // Enables automatic retries, None if it wasn't specified
+    max_attempts: Option<u32>,
+    // Where to keep track of downloaded videos, None if it wasn't specified
+    download_archive: Option<ArchivePreference>,
+    // Seconds after which a download is stopped, None if they weren't specified
+    timeout: Option<u64>,
+    stall_timeout: Option<u64>,
+    // The name of the job keeping track of the download, None if it wasn't specified
+    job_name: Option<String>,
+    // Whether an existing job called job_name can be overwritten
+    replace_job: bool,
+    // Where to save a summary of the download, None if it wasn't asked for
+    report: Option<ReportPreference>,
+    output_format: OutputFormat,
+    // None when blob-dl is asked to download something
+    subcommand: Option<CliSubcommand>,
 
     pub config_file_preference: ConfigFilePreferences,
 }
@@ -100,9 +478,68 @@ impl CliConfig {
     /// Constructs a CliConfig object based on Clap's output
     pub fn from(matches: ArgMatches) -> BlobResult<CliConfig> {
 
-        let url = match matches.get_one::<String>("URL") {
-            Some(url) => url.clone(),
-            None => return Err(BlobdlError::MissingArgument),
+        let subcommand = match matches.subcommand() {
+            Some(("archive", archive)) => Some(CliSubcommand::Archive {
+                location: archive.get_one::<PathBuf>("ARCHIVE").cloned(),
+                remove: archive.get_many::<String>("remove").map(|ids| ids.cloned().collect()).unwrap_or_default(),
+                prune: archive.get_flag("prune"),
+            }),
+            Some(("sync", sync)) => Some(CliSubcommand::Sync {
+                url: sync.get_one::<String>("URL").cloned().unwrap_or_default(),
+                directory: sync.get_one::<PathBuf>("DIR").cloned().unwrap_or_default(),
+                dry_run: sync.get_flag("dry-run"),
+                removed: match sync.get_one::<String>("removed").map(String::as_str) {
+                    Some("move") => RemovedEntries::Move,
+                    Some("delete") => RemovedEntries::Delete,
+                    _ => RemovedEntries::Keep,
+                },
+                jobs: sync.get_one::<u64>("jobs").map(|jobs| *jobs as usize),
+                config_file_preference: subcommand_config_file_preference(sync),
+            }),
+            Some(("resume", resume)) => Some(CliSubcommand::Resume {
+                job: resume.get_one::<String>("JOB").cloned(),
+            }),
+            Some(("history", history)) => Some(CliSubcommand::History {
+                filter: HistoryFilter {
+                    since: history.get_one::<String>("since").cloned(),
+                    until: history.get_one::<String>("until").cloned(),
+                    url: history.get_one::<String>("url").cloned(),
+                    status: history.get_one::<String>("status").cloned(),
+                },
+                show: history.subcommand_matches("show").and_then(|show| show.get_one::<usize>("ID").copied()),
+            }),
+            Some(("retry-failed", retry)) => Some(CliSubcommand::RetryFailed {
+                job: retry.get_one::<String>("JOB").cloned(),
+            }),
+            Some(("watch", watch)) => Some(CliSubcommand::Watch {
+                urls: watch.get_many::<String>("URL").map(|urls| urls.cloned().collect()).unwrap_or_default(),
+                interval: watch.get_one::<u64>("interval").copied().unwrap_or(3600),
+                once: watch.get_flag("once"),
+                jobs: watch.get_one::<u64>("jobs").map(|jobs| *jobs as usize),
+                config_file_preference: subcommand_config_file_preference(watch),
+            }),
+            Some(("queue", queue)) => Some(CliSubcommand::Queue(match queue.subcommand() {
+                Some(("add", add)) => QueueAction::Add {
+                    url: add.get_one::<String>("URL").cloned().unwrap_or_default(),
+                    config_file_preference: match add.get_one::<String>("profile") {
+                        Some(profile) => ConfigFilePreferences::Profile(profile.clone()),
+                        None => subcommand_config_file_preference(add),
+                    },
+                },
+                Some(("remove", remove)) => QueueAction::Remove {
+                    ids: remove.get_many::<usize>("ID").map(|ids| ids.copied().collect()).unwrap_or_default(),
+                },
+                Some(("run", _)) => QueueAction::Run,
+                _ => QueueAction::List,
+            })),
+            _ => None,
+        };
+
+        // Subcommands don't need a URL
+        let url = match (matches.get_one::<String>("URL"), &subcommand) {
+            (Some(url), _) => url.clone(),
+            (None, Some(_)) => String::new(),
+            (None, None) => return Err(BlobdlError::MissingArgument),
         };
 
         let verbosity = {
@@ -118,6 +555,40 @@ impl CliConfig {
         };
         let show_command = matches.get_flag("show-command");
 
+        let mut clip_sections = vec![];
+        if let Some(ranges) = matches.get_many::<String>("clip") {
+            for range in ranges {
+                clip_sections.push(ClipSection::parse(range).ok_or(BlobdlError::InvalidClipSection)?);
+            }
+        }
+
+        let jobs = matches.get_one::<u64>("jobs").map(|jobs| *jobs as usize);
+        let max_attempts = matches.get_one::<u32>("max-attempts").copied();
+        let download_archive = matches.get_one::<String>("download-archive").map(|archive| ArchivePreference::from_argument(archive));
+        let job_name = matches.get_one::<String>("job").cloned();
+        let replace_job = matches.get_flag("replace-job");
+        let timeout = matches.get_one::<u64>("timeout").copied();
+        let stall_timeout = matches.get_one::<u64>("stall-timeout").copied();
+
+        let report = match matches.get_many::<String>("report").map(|values| values.collect::<Vec<_>>()) {
+            Some(values) => Some(ReportPreference {
+                format: match values[0].as_str() {
+                    "json" => ReportFormat::Json,
+                    "markdown" | "md" => ReportFormat::Markdown,
+                    _ => return Err(BlobdlError::UnknownReportFormat),
+                },
+                path: PathBuf::from(values[1]),
+            }),
+            None => None,
+        };
+
+        let output_format = match matches.get_one::<String>("output-format").map(String::as_str) {
+            // Events need stdout to themselves, which only works where it can be redirected (see events::enable)
+            Some("json") if cfg!(unix) => OutputFormat::Json,
+            Some("json") => return Err(BlobdlError::JsonOutputUnsupported),
+            _ => OutputFormat::Human,
+        };
+
         // The user is supposed to only use one of these at a time
         let mut config_file_preference = ConfigFilePreferences::NoConfig;
         if let Some(path) = matches.get_one::<PathBuf>("locate-config-file") {
@@ -133,6 +604,17 @@ impl CliConfig {
             url,
             verbosity,
             show_command,
+            clip_sections,
+            jobs,
+            max_attempts,
+            download_archive,
+            timeout,
+            stall_timeout,
+            job_name,
+            replace_job,
+            report,
+            output_format,
+            subcommand,
             config_file_preference
         })
     }
@@ -146,14 +628,91 @@ impl CliConfig {
     pub fn show_command(&self) -> bool {
         self.show_command
     }
+    pub(crate) fn clip_sections(&self) -> &Vec<ClipSection> {
+        &self.clip_sections
+    }
+    pub fn jobs(&self) -> Option<usize> {
+        self.jobs
+    }
+    pub fn max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+    pub(crate) fn download_archive(&self) -> Option<&ArchivePreference> {
+        self.download_archive.as_ref()
+    }
+    pub fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+    pub fn stall_timeout(&self) -> Option<u64> {
+        self.stall_timeout
+    }
+    pub fn job_name(&self) -> Option<&str> {
+        self.job_name.as_deref()
+    }
+    pub fn replace_job(&self) -> bool {
+        self.replace_job
+    }
+    pub fn report(&self) -> Option<&ReportPreference> {
+        self.report.as_ref()
+    }
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+    pub fn subcommand(&self) -> Option<&CliSubcommand> {
+        self.subcommand.as_ref()
+    }
     pub fn config_file_preference(&self) -> &ConfigFilePreferences {
         &self.config_file_preference
     }
 }
 
+/// The config file a subcommand which downloads something was asked to use, through -c or -l
+fn subcommand_config_file_preference(matches: &ArgMatches) -> ConfigFilePreferences {
+    if let Some(path) = matches.get_one::<PathBuf>("locate-config-file") {
+        ConfigFilePreferences::CustomConfig(path.clone())
+    } else if matches.get_flag("use-config-file") {
+        ConfigFilePreferences::DefaultConfig
+    } else {
+        ConfigFilePreferences::NoConfig
+    }
+}
+
+/// Reads how often `blob-dl watch` checks its targets, as a number of seconds
+fn parse_interval(interval: &str) -> Result<u64, String> {
+    let (number, unit) = match interval.find(|c: char| !c.is_ascii_digit()) {
+        Some(position) => interval.split_at(position),
+        None => (interval, "s"),
+    };
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(String::from("the interval's unit can be s, m, h or d")),
+    };
+
+    match number.parse::<u64>().ok().and_then(|number| number.checked_mul(multiplier)) {
+        Some(seconds) if seconds > 0 => Ok(seconds),
+        _ => Err(String::from("the interval has to be a positive number, e.g. 30m")),
+    }
+}
+
+/// Makes sure that a date passed to `blob-dl history` is written as YYYY-MM-DD
+fn parse_date(date: &str) -> Result<String, String> {
+    let parts: Vec<&str> = date.split('-').collect();
+    let valid = parts.len() == 3
+        && [4, 2, 2].iter().zip(&parts).all(|(len, part)| part.len() == *len && part.chars().all(|c| c.is_ascii_digit()));
+
+    if valid {
+        Ok(date.to_string())
+    } else {
+        Err(String::from("dates are written as YYYY-MM-DD"))
+    }
+}
+
 /// Check if the user has a version of ytdlp compatible with blob-dl (now it is 22025.03.31)
 pub fn is_ytdlp_compatible() ->Result<bool, BlobdlError> {
-    let version = std::process::Command::new("yt-dlp")
+    let version = crate::ytdlp::command()
             .arg("--version")
             .output();
     
@@ -0,0 +1,254 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::analyzer;
+use crate::app::ExitStatus;
+use crate::assembling;
+use crate::assembling::youtube::config::DownloadConfig;
+use crate::dispatcher;
+use crate::error::{BlobResult, BlobdlError};
+use crate::history;
+use crate::job::{EntryStatus, Job};
+use crate::parser::{ReportPreference, Verbosity};
+use crate::run;
+
+/// The queue is saved in blob-dl's data directory
+const QUEUE_FILE_NAME: &str = "queue.json";
+
+fn queue_path() -> BlobResult<PathBuf> {
+    dispatcher::get_data_dir().map(|dir| dir.join(QUEUE_FILE_NAME))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum QueueStatus {
+    /// It wasn't downloaded yet
+    Pending,
+    /// Everything was downloaded
+    Done,
+    /// Some videos couldn't be downloaded, the next `queue run` tries again
+    Failed,
+}
+
+impl QueueStatus {
+    fn name(&self) -> &'static str {
+        match self {
+            QueueStatus::Pending => "pending",
+            QueueStatus::Done => "done",
+            QueueStatus::Failed => "failed",
+        }
+    }
+}
+
+/// What queue.json contains
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Queue {
+    /// The id of the next entry, ids are never reused so that a new entry doesn't get the job of a removed one
+    next_id: usize,
+    entries: Vec<QueueEntry>,
+}
+
+/// A link waiting to be downloaded
+#[derive(Debug, Serialize, Deserialize)]
+struct QueueEntry {
+    /// Entries are numbered starting from 1
+    id: usize,
+    url: String,
+    /// Unix timestamp of when the entry was added
+    added: u64,
+    /// The profile the preferences come from, if there was one
+    profile: Option<String>,
+    /// Resolved when the entry was added, so that running the queue doesn't ask anything
+    download_config: DownloadConfig,
+    status: QueueStatus,
+    /// How many times `queue run` tried to download this entry
+    attempts: u32,
+    /// Why the last attempt failed
+    error: Option<String>,
+}
+
+impl QueueEntry {
+    /// Every entry has its own job, which makes later attempts skip what was already downloaded
+    fn job_name(&self) -> String {
+        format!("queue-{}", self.id)
+    }
+}
+
+fn read_queue() -> BlobResult<Queue> {
+    let contents = match fs::read_to_string(queue_path()?) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Queue { next_id: 1, entries: vec![] }),
+        Err(err) => return Err(err.into()),
+    };
+
+    // The queue used to be saved as a plain list of entries
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Saved {
+        Queue(Queue),
+        Entries(Vec<QueueEntry>),
+    }
+    let mut queue = match serde_json::from_str(&contents)? {
+        Saved::Queue(queue) => queue,
+        Saved::Entries(entries) => Queue { next_id: 0, entries },
+    };
+    let last_id = queue.entries.iter().map(|entry| entry.id).max().unwrap_or(0);
+    queue.next_id = queue.next_id.max(last_id + 1);
+    Ok(queue)
+}
+
+fn save_queue(queue: &Queue) -> BlobResult<()> {
+    let path = queue_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(queue)?)?;
+    Ok(())
+}
+
+/// `blob-dl queue add <url>`: asks the usual questions (unless `user_config` answers them) and saves the result for later
+pub(crate) fn add(url: &str, profile: Option<&str>, user_config: Option<DownloadConfig>) -> BlobResult<()> {
+    let download_option = analyzer::analyze_url(url)?;
+    let (_, download_config) = assembling::generate_command(url, &download_option, user_config.unwrap_or_else(DownloadConfig::empty))?;
+
+    let mut queue = read_queue()?;
+    let id = queue.next_id;
+    queue.next_id += 1;
+    queue.entries.push(QueueEntry {
+        id,
+        url: url.to_string(),
+        added: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        profile: profile.map(str::to_string),
+        download_config,
+        status: QueueStatus::Pending,
+        attempts: 0,
+        error: None,
+    });
+    save_queue(&queue)?;
+
+    println!("{} Added {} to the queue as #{}, download it with `blob-dl queue run`", "[blob-dl]".purple(), url, id);
+    Ok(())
+}
+
+/// `blob-dl queue list`
+pub(crate) fn list() -> BlobResult<()> {
+    let entries = read_queue()?.entries;
+    if entries.is_empty() {
+        println!("{} The queue is empty, add something with `blob-dl queue add URL`", "[blob-dl]".purple());
+        return Ok(());
+    }
+
+    for entry in &entries {
+        let status = match entry.status {
+            QueueStatus::Pending => entry.status.name().cyan(),
+            QueueStatus::Done => entry.status.name().green(),
+            QueueStatus::Failed => entry.status.name().red(),
+        };
+        println!("{:>4}  {}  {:<7}  {}  ({} attempt(s){})",
+                 entry.id,
+                 history::format_date(entry.added),
+                 status,
+                 entry.url,
+                 entry.attempts,
+                 entry.profile.as_ref().map(|profile| format!(", profile {}", profile)).unwrap_or_default());
+        if let Some(error) = &entry.error {
+            println!("      {}", error.red());
+        }
+    }
+    Ok(())
+}
+
+/// `blob-dl queue remove <id>...`: the jobs of the removed entries are deleted too
+pub(crate) fn remove(ids: &[usize]) -> BlobResult<()> {
+    let mut queue = read_queue()?;
+    if ids.iter().any(|id| !queue.entries.iter().any(|entry| entry.id == *id)) {
+        return Err(BlobdlError::QueueEntryNotFound);
+    }
+
+    for entry in queue.entries.iter().filter(|entry| ids.contains(&entry.id)) {
+        Job::delete(&entry.job_name())?;
+    }
+    queue.entries.retain(|entry| !ids.contains(&entry.id));
+    save_queue(&queue)?;
+    println!("{} Removed {} entr{} from the queue", "[blob-dl]".purple(), ids.len(), if ids.len() == 1 { "y" } else { "ies" });
+    Ok(())
+}
+
+/// `blob-dl queue run`: downloads every entry which is pending or failed last time, one after the other.
+///
+/// The queue is saved after every entry, so stopping blob-dl doesn't lose track of what was done.
+/// Every entry overwrites the `report`, so it is about the last one which was downloaded.
+/// The exit status is a partial failure if some entries failed and others didn't
+pub(crate) fn run(verbosity: &Verbosity, report: Option<&ReportPreference>) -> BlobResult<ExitStatus> {
+    let mut queue = read_queue()?;
+    let to_run: Vec<usize> = queue.entries.iter()
+        .filter(|entry| entry.status != QueueStatus::Done)
+        .map(|entry| entry.id)
+        .collect();
+
+    if to_run.is_empty() {
+        println!("{} There is nothing to download in the queue", "[blob-dl]".purple());
+        return Ok(ExitStatus::Success);
+    }
+
+    let mut statuses = vec![];
+    for (position, id) in to_run.iter().enumerate() {
+        let Some(entry) = queue.entries.iter_mut().find(|entry| entry.id == *id) else { continue };
+        println!("{} Downloading #{} ({} of {}): {}", "[blob-dl]".purple(), entry.id, position + 1, to_run.len(), entry.url);
+
+        let result = download(entry, verbosity, report);
+        if run::interrupted() {
+            // The job remembers what was downloaded, the entry is simply picked up again by the next run
+            save_queue(&queue)?;
+            return Ok(ExitStatus::Interrupted);
+        }
+
+        entry.attempts += 1;
+        match result {
+            Ok((job, status)) => {
+                statuses.push(status);
+                let failed = job.state.entries.iter().filter(|video| video.status != EntryStatus::Downloaded).count();
+                if failed == 0 {
+                    entry.status = QueueStatus::Done;
+                    entry.error = None;
+                } else {
+                    entry.status = QueueStatus::Failed;
+                    entry.error = Some(format!("{} video(s) weren't downloaded", failed));
+                }
+            }
+            Err(err) => {
+                err.report();
+                statuses.push(err.exit_status());
+                entry.status = QueueStatus::Failed;
+                entry.error = Some(err.message());
+            }
+        }
+        save_queue(&queue)?;
+    }
+
+    let done = queue.entries.iter().filter(|entry| to_run.contains(&entry.id) && entry.status == QueueStatus::Done).count();
+    println!("{} {} of {} queued download(s) finished, see `blob-dl queue list`", "[blob-dl]".purple(), done, to_run.len());
+    Ok(statuses.into_iter().reduce(ExitStatus::combine).unwrap_or(ExitStatus::Success))
+}
+
+fn download(entry: &QueueEntry, verbosity: &Verbosity, report: Option<&ReportPreference>) -> BlobResult<(Job, ExitStatus)> {
+    let mut download_config = entry.download_config.clone();
+    // Nobody is there to choose which videos to download again
+    if download_config.advanced.retry.is_none() {
+        download_config.advanced.retry = Some(Default::default());
+    }
+
+    // The job of an earlier attempt knows what was already downloaded
+    let mut job = match Job::load(Some(&entry.job_name())) {
+        Ok(job) => job,
+        Err(BlobdlError::JobNotFound) => Job::create(Some(&entry.job_name()), download_config, false)?,
+        Err(err) => return Err(err),
+    };
+    let command = job.state.download_config.build_command()?.0;
+    let status = run::run_and_observe(command, &mut job, verbosity, report)?;
+    Ok((job, status))
+}
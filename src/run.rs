@@ -1,233 +1,973 @@
-use std::process::{Command, Stdio};
-use std::io::{BufRead, BufReader};
-use dialoguer::{theme::ColorfulTheme, MultiSelect};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
 use dialoguer::console::Term;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use colored::Colorize;
+use serde::Deserialize;
 
-use crate::youtube_error_message::*;
 use crate::ui_prompts::*;
 use crate::parser;
-use crate::error::{BlobResult, YtdlpError};
-use crate::assembling::youtube::config;
+use crate::error::{self, BlobResult, BlobdlError, YtdlpError};
+use crate::assembling::youtube::{self, config};
+use crate::tracklist;
+use crate::m3u;
+use crate::archive;
+use crate::job::{EntryStatus, Job};
+use crate::history;
+use crate::analyzer::DownloadOption;
+use crate::classifier;
+use crate::app::ExitStatus;
+use crate::events;
+
+mod progress;
+mod output;
+mod retry;
+mod interrupt;
+mod summary;
+mod hooks;
+mod remediation;
+use progress::{ProgressEvent, ProgressTracker};
+pub(crate) use progress::format_bytes;
+pub(crate) use interrupt::interrupted;
+use output::OutputLine;
+use summary::RunSummary;
+use remediation::Remediation;
+
+/// How often the download logs of the running processes are checked for files yt-dlp finished
+const DOWNLOAD_LOG_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// yt-dlp appends a line formatted like this to a log file for every file it finishes (after it has been moved to its final location)
+const DOWNLOADED_FILE_TEMPLATE: &str = "after_move:%(.{id,title,filepath,duration,playlist,playlist_index,description,sponsorblock_chapters,format_id,height,vcodec,acodec})j";
+
+/// Used to give every download log a different name
+static DOWNLOAD_LOG_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// What yt-dlp reports about a file once it is done with it, see DOWNLOADED_FILE_TEMPLATE
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct DownloadedFile {
+    pub(crate) id: String,
+    pub(crate) title: Option<String>,
+    pub(crate) filepath: PathBuf,
+    /// Measured in seconds
+    pub(crate) duration: Option<f64>,
+    /// The title of the playlist the video was downloaded from, if it was
+    #[serde(default)]
+    pub(crate) playlist: Option<String>,
+    /// Only present when the video was downloaded as part of a playlist
+    pub(crate) playlist_index: Option<usize>,
+    pub(crate) description: Option<String>,
+    /// Only present when SponsorBlock was used
+    pub(crate) sponsorblock_chapters: Option<Vec<SponsorBlockSegment>>,
+    /// The format yt-dlp picked, `137+140` if video and audio were merged
+    #[serde(default)]
+    pub(crate) format_id: Option<String>,
+    #[serde(default)]
+    pub(crate) height: Option<u32>,
+    #[serde(default)]
+    pub(crate) vcodec: Option<String>,
+    #[serde(default)]
+    pub(crate) acodec: Option<String>,
+}
+
+/// A segment of a video found by SponsorBlock
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct SponsorBlockSegment {
+    pub(crate) start_time: f64,
+    pub(crate) end_time: f64,
+    pub(crate) category: String,
+}
+
+/// Everything that was learned by running a yt-dlp command
+struct CommandOutcome {
+    errors: Vec<YtdlpError>,
+    downloaded: Vec<DownloadedFile>,
+    /// How many entries the playlist has, if yt-dlp said so
+    playlist_count: Option<usize>,
+    /// The user pressed Ctrl-C before every command was done
+    interrupted: bool,
+    /// What yt-dlp printed as WARNING:, each one only once
+    warnings: Vec<String>,
+    /// The commands which were started, as they were run (see history::command_line)
+    commands: Vec<Vec<String>>,
+}
 
 /// Executes the yt-dlp command and analyzes its output.
 ///
 /// It filters what to show to the user according to verbosity options
 ///
-/// It records which videos fail to download and the reason: if trying again can fix the issue the user can choose to retry
-pub fn run_and_observe(command: &mut Command, download_config: &config::DownloadConfig, verbosity: &parser::Verbosity) -> BlobResult<()> {
-    // Run the command and record any errors
-    if let Some(errors) = run_command(command, verbosity) {
-        // Some videos could not be downloaded, ask the user which ones they want to try to re-download
-        let user_selection = ask_for_redownload(&errors);
-
-        // The list of commands that have to be re-run in case of errors
-        let mut to_be_downloaded = Vec::new();
+/// It records which videos fail to download and the reason: if trying again can fix the issue the user can choose to retry.
+/// What happens to every video is saved in `job`, so that the download can be resumed if it is interrupted.
+/// A summary is printed at the end, and saved according to `report` if the user asked for it.
+/// Returns the status blob-dl should exit with, which depends on how many videos failed
+pub fn run_and_observe(command: Command,
+                       job: &mut Job,
+                       verbosity: &parser::Verbosity,
+                       report: Option<&parser::ReportPreference>) -> BlobResult<ExitStatus> {
+    // The job is updated while the config is in use
+    let download_config = &job.state.download_config.clone();
+    // Ctrl-C stops the download, but the summary is still shown
+    let _guard = interrupt::catch();
+    let started = SystemTime::now();
+    // How many yt-dlp processes can run at the same time
+    let jobs = download_config.advanced.parallel_downloads.unwrap_or(1).max(1);
+    let is_playlist = download_config.download_target == Some(DownloadOption::YtPlaylist);
+    let supervision = download_config.advanced.supervision.as_ref();
+    events::emit(events::Event::RunStarted(events::RunStarted {
+        url: download_config.url.clone(),
+        job: job.state.name.clone(),
+        playlist: is_playlist,
+    }));
 
-        // Selection 0 and 1 are hard-coded (select all | select nothing)
-        if !user_selection.is_empty() {
-            if user_selection[0] == 0 {
-                // The user wants to re-download all the videos
-                for video_to_re_download in &errors {
-                    // Re-download every video while keeping the current command configuration (quality, naming preference, ...)
-                    to_be_downloaded.push(download_config.build_command_for_video(video_to_re_download.video_id())?);
-                }
-            } else if user_selection[0] == 1 {
-                // The user doesn't want to re-download anything
-            } else {
-                // Only re-download the selected videos
-                for i in user_selection {
-                    // Skip 0 and 1 because they are hard-coded options (select all or nothing)
-                    if i == 0 || i == 1 {
-                        continue;
-                    }
-                    // There is a 1:1 correspondence between the number in user_selection and
-                    // the index of the video it refers to in errors
-                    to_be_downloaded.push(download_config.build_command_for_video(errors[i - 2].video_id().as_str())?);
-                }
+    // Videos which are already in the download archive, or which an earlier run of the job downloaded, are skipped
+    let archive_path = download_config.archive_path()?;
+    let mut items = None;
+    let mut done = job.downloaded_ids();
+    let mut skipped = 0;
+    if archive_path.is_some() || !done.is_empty() {
+        let url = download_config.url.as_ref().ok_or(BlobdlError::UrlNotProvided)?;
+        let listed = youtube::get_playlist_items(url, is_playlist)?;
+        if let Some(archive_path) = &archive_path {
+            if let Some(parent) = archive_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
             }
+            let loaded = archive::Archive::read(archive_path)?;
+            done.extend(listed.iter().filter(|item| loaded.contains(&item.id)).map(|item| item.id.clone()));
+        }
+
+        let already_downloaded = listed.iter().filter(|item| done.contains(&item.id)).count();
+        println!("{} {} new, {} already downloaded", "[blob-dl]".purple(), listed.len() - already_downloaded, already_downloaded);
+        if !listed.is_empty() && already_downloaded == listed.len() {
+            println!("{} There is nothing new to download", "[blob-dl]".purple());
+            job.record(&[], &[])?;
+            save_to_history(vec![], job, &[], started);
+            let status = finish_run(RunSummary::new(job, &[], already_downloaded, &[], started), report);
+            run_final_hooks(download_config, job, 0, already_downloaded, verbosity);
+            job.remove_if_complete();
+            return Ok(status);
         }
-        for mut com in to_be_downloaded {
-            run_command(&mut com, verbosity);
+        skipped = already_downloaded;
+        job.add_entries(&listed)?;
+        items = Some(listed);
+    }
+
+    // Run the command and record any errors
+    // Supervised playlists are downloaded one entry per process, so that stopping one doesn't stop the whole playlist.
+    // Without an archive yt-dlp can't skip what the job already downloaded by itself, so entries are downloaded one by one too
+    let one_per_entry = jobs > 1 || supervision.is_some() || (archive_path.is_none() && !done.is_empty());
+    let outcome = if one_per_entry && is_playlist {
+        run_playlist_in_parallel(command, download_config, items, &done, jobs, verbosity, job)?
+    } else {
+        run_commands(vec![(command, CurrentItem::default())], 1, None, verbosity, &download_config.advanced, Some(&mut *job))?
+    };
+    let mut downloaded = outcome.downloaded;
+    let mut warnings = outcome.warnings;
+    let mut commands = outcome.commands;
+    let mut redownloader = Redownloader::new(download_config, outcome.playlist_count);
+
+    if outcome.interrupted {
+        // Nothing else is downloaded
+    } else if let (false, Some(policy)) = (outcome.errors.is_empty(), &download_config.advanced.retry) {
+        // Retry without asking anything
+        let retried = retry::retry_failed(outcome.errors, policy, &mut redownloader, jobs, verbosity, job)?;
+        downloaded.extend(retried.downloaded);
+        warnings.extend(retried.warnings);
+        commands.extend(retried.commands);
+    } else if !outcome.errors.is_empty() {
+        // Some videos could not be downloaded, ask the user which ones they want to try to re-download and how
+        let mut to_be_downloaded = Vec::new();
+        for (error, redownload_config) in ask_for_redownload(&outcome.errors, download_config) {
+            to_be_downloaded.push(redownloader.command_with(&redownload_config, error)?);
         }
-        // If no errors occurred, there is nothing to return
-        Ok(())
+        let redownloaded = run_commands(to_be_downloaded, jobs, None, verbosity, &download_config.advanced, Some(&mut *job))?;
+        downloaded.extend(redownloaded.downloaded);
+        warnings.extend(redownloaded.warnings);
+        commands.extend(redownloaded.commands);
     } else {
         #[cfg(debug_assertions)]
         println!("The command ran without any errors!! :)");
-        Ok(())
     }
+
+    if interrupt::interrupted() {
+        clean_up_after_interruption(download_config, started);
+    } else {
+        if download_config.split_by_tracklist == Some(true) {
+            tracklist::split_downloads(&downloaded);
+        }
+    }
+
+    // Record the playlist's order, which is otherwise lost when indexes aren't included in filenames
+    if is_playlist {
+        m3u::write_playlists(&downloaded);
+    }
+
+    job.record(&downloaded, &[])?;
+    save_to_history(commands, job, &downloaded, started);
+    let status = finish_run(RunSummary::new(job, &downloaded, skipped, &warnings, started), report);
+    run_final_hooks(download_config, job, downloaded.len(), skipped, verbosity);
+    job.print_status();
+    job.remove_if_complete();
+    Ok(status)
 }
 
-/// Returns whether it makes sense to try downloading the video again
-fn is_recoverable(error: &YtdlpError, table: &HashMap<&'static str, bool>) -> bool {
-    if error.error_msg().contains(VIDEO_UNAVAILABLE) {
-        return false;
+/// Removes the fragments of the downloads which were interrupted, if the user wants to
+fn clean_up_after_interruption(download_config: &config::DownloadConfig, started: SystemTime) {
+    println!("{} The download was interrupted, what was downloaded so far was saved", "[blob-dl]".purple());
+
+    // Without a terminal there is nobody to ask, fragments are kept so that yt-dlp can continue from them
+    let term = Term::stderr();
+    if !term.is_term() {
+        return;
     }
-    if let Some(result) = table.get(error.error_msg().as_str()) {
-        if !(*result) {
-            // The error is documented and unrecoverable
-            false
-        } else {
-            // The error is documented and recoverable
-            true
-        }
+    let remove = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(REMOVE_FRAGMENTS_PROMPT)
+        .default(false)
+        .interact_on(&term)
+        .unwrap_or(false);
+
+    if remove {
+        let directory = download_config.output_path.as_deref().unwrap_or(".");
+        let removed = interrupt::remove_fragments(std::path::Path::new(directory), started);
+        println!("{} Deleted {} partially downloaded file(s)", "[blob-dl]".purple(), removed);
+    }
+}
+
+/// Shows the summary of a run (as an event with --output-format json) and exports it if the user asked for it,
+/// returns the status the run ended with
+fn finish_run(summary: RunSummary, report: Option<&parser::ReportPreference>) -> ExitStatus {
+    if events::enabled() {
+        events::emit(summary.to_event());
     } else {
-        // By default undocumented errors are flagged as unrecoverable
-        false
-    }
-}
-
-/// A list of all the documented youtube error messages and whether they are recoverable.
-fn init_error_msg_lut() -> HashMap<&'static str, bool> {
-    HashMap::from([
-        (PRIVATE_VIDEO,          false),
-        (NONEXISTENT_PLAYLIST,   false),
-        (HOMEPAGE_REDIRECT,      false),
-        (VIOLENT_VIDEO,          false),
-        (REMOVED_VIDEO,          false),
-        (YTDLP_GAVE_UP,          false),
-        (VIDEO_NOT_FOUND,        false),
-        (NETWORK_FAIL,           true),
-        (NO_API_PAGE,            false),
-        (ENCODER_STREAM_ERROR,   false),
-        (NONEXISTENT_VIDEO,      false),
-        (NONEXISTENT_FORMAT,     false),
-    ])
-}
-
-/// Runs the command and displays the output to the console.
+        summary.print();
+    }
+    if let Some(report) = report {
+        summary.write_report(&report.format, &report.path);
+    }
+    summary.exit_status()
+}
+
+/// Runs on_error for every video of the job which failed, then on_run_complete
+fn run_final_hooks(download_config: &config::DownloadConfig, job: &Job, downloaded: usize, skipped: usize, verbosity: &parser::Verbosity) {
+    let Some(hooks) = &download_config.advanced.hooks else { return };
+
+    for entry in job.state.entries.iter().filter(|entry| entry.status == EntryStatus::Failed) {
+        for message in hooks::on_error(hooks, job, entry, verbosity) {
+            println!("{}", message);
+        }
+    }
+    for message in hooks::on_run_complete(hooks, job, downloaded, skipped, verbosity) {
+        println!("{}", message);
+    }
+}
+
+/// Adds the run to the download history, the download itself already happened so problems are only reported
+fn save_to_history(commands: Vec<Vec<String>>, job: &Job, downloaded: &[DownloadedFile], started: SystemTime) {
+    if let Err(err) = history::record_run(commands, job, downloaded, started) {
+        err.report();
+    }
+}
+
+/// `blob-dl retry-failed`: downloads the videos of `job` which failed again, with the job's preferences
+pub(crate) fn retry_job_failures(job: &mut Job,
+                                 verbosity: &parser::Verbosity,
+                                 report: Option<&parser::ReportPreference>) -> BlobResult<ExitStatus> {
+    let failed = job.failed_entries();
+    if failed.is_empty() {
+        println!("{} No video of job {} failed", "[blob-dl]".purple(), job.state.name);
+        return Ok(ExitStatus::Success);
+    }
+    println!("{} Downloading {} video(s) of job {} again", "[blob-dl]".purple(), failed.len(), job.state.name);
+
+    let download_config = &job.state.download_config.clone();
+    events::emit(events::Event::RunStarted(events::RunStarted {
+        url: download_config.url.clone(),
+        job: job.state.name.clone(),
+        playlist: download_config.download_target == Some(DownloadOption::YtPlaylist),
+    }));
+    let jobs = download_config.advanced.parallel_downloads.unwrap_or(1).max(1);
+    let last_index = job.state.entries.iter().filter_map(|entry| entry.playlist_index).max();
+    let mut redownloader = Redownloader::new(download_config, last_index);
+
+    let _guard = interrupt::catch();
+    let started = SystemTime::now();
+    let mut commands = vec![];
+    for entry in &failed {
+        commands.push(redownloader.command_for_entry(&entry.id, entry.playlist_index)?);
+    }
+    let outcome = run_commands(commands, jobs, Some(failed.len()), verbosity, &download_config.advanced, Some(&mut *job))?;
+
+    if download_config.download_target == Some(DownloadOption::YtPlaylist) {
+        m3u::write_playlists(&outcome.downloaded);
+    }
+    job.record(&outcome.downloaded, &[])?;
+    save_to_history(outcome.commands, job, &outcome.downloaded, started);
+    let status = finish_run(RunSummary::new(job, &outcome.downloaded, 0, &outcome.warnings, started), report);
+    run_final_hooks(download_config, job, outcome.downloaded.len(), 0, verbosity);
+    job.print_status();
+    job.remove_if_complete();
+    Ok(status)
+}
+
+/// Splits the playlist into its entries and downloads `jobs` of them at a time, each with its own yt-dlp process.
+///
+/// Files get the same names they would have had if the playlist was downloaded by a single process.
+/// `items` are fetched here if they haven't been listed already, the ones in `done` (ids of videos which were already downloaded) are skipped.
+/// If the entries can't be listed the playlist is downloaded by `command` as usual
+fn run_playlist_in_parallel(command: Command,
+                            download_config: &config::DownloadConfig,
+                            items: Option<Vec<youtube::PlaylistItem>>,
+                            done: &HashSet<String>,
+                            jobs: usize,
+                            verbosity: &parser::Verbosity,
+                            job: &mut Job) -> BlobResult<CommandOutcome> {
+    let items = match items {
+        Some(items) => items,
+        None => {
+            let url = download_config.url.as_ref().ok_or(BlobdlError::UrlNotProvided)?;
+            match youtube::get_playlist_items(url, true) {
+                Ok(items) => {
+                    job.add_entries(&items)?;
+                    items
+                }
+                Err(_) => vec![],
+            }
+        }
+    };
+
+    // The entries couldn't be listed
+    if items.is_empty() {
+        return run_commands(vec![(command, CurrentItem::default())], 1, None, verbosity, &download_config.advanced, Some(job));
+    }
+
+    // yt-dlp pads indexes according to the last one
+    let last_index = items.iter().map(|item| item.playlist_index).max().unwrap_or(items.len());
+
+    let commands = items.iter()
+        .filter(|item| !done.contains(&item.id))
+        .map(|item| {
+            let command = download_config.build_command_for_playlist_item(item.playlist_index, last_index)?;
+            Ok((command, CurrentItem {
+                video_id: None,
+                playlist_index: Some(item.playlist_index),
+                playlist_count: Some(last_index),
+                single_entry: true,
+            }))
+        })
+        .collect::<BlobResult<Vec<(Command, CurrentItem)>>>()?;
+    let item_count = commands.len();
+
+    run_commands(commands, jobs, Some(item_count), verbosity, &download_config.advanced, Some(job))
+}
+
+/// Runs commands which download single entries of a playlist for `job`, `jobs` at a time,
+/// then ends the run like any other download: summary, report, history and hooks.
+///
+/// Every command comes with the index of the entry it downloads, `last_index` is the index of the playlist's last entry
+/// and `skipped` how many entries didn't have to be downloaded.
+/// Returns the files which were downloaded and the status the run ended with
+pub(crate) fn run_playlist_entries(commands: Vec<(Command, usize)>,
+                                   last_index: usize,
+                                   skipped: usize,
+                                   jobs: usize,
+                                   job: &mut Job,
+                                   verbosity: &parser::Verbosity,
+                                   report: Option<&parser::ReportPreference>) -> BlobResult<(Vec<DownloadedFile>, ExitStatus)> {
+    let download_config = &job.state.download_config.clone();
+    events::emit(events::Event::RunStarted(events::RunStarted {
+        url: download_config.url.clone(),
+        job: job.state.name.clone(),
+        playlist: true,
+    }));
+    let _guard = interrupt::catch();
+    let started = SystemTime::now();
+
+    let item_count = commands.len();
+    let commands = commands.into_iter()
+        .map(|(command, index)| (command, CurrentItem {
+            video_id: None,
+            playlist_index: Some(index),
+            playlist_count: Some(last_index),
+            single_entry: true,
+        }))
+        .collect();
+    let outcome = run_commands(commands, jobs.max(1), Some(item_count), verbosity, &download_config.advanced, Some(&mut *job))?;
+
+    job.record(&outcome.downloaded, &[])?;
+    save_to_history(outcome.commands, job, &outcome.downloaded, started);
+    let status = finish_run(RunSummary::new(job, &outcome.downloaded, skipped, &outcome.warnings, started), report);
+    run_final_hooks(download_config, job, outcome.downloaded.len(), skipped, verbosity);
+    job.remove_if_complete();
+    Ok((outcome.downloaded, status))
+}
+
+/// Builds the commands which download failed videos again.
+///
+/// Playlist entries are downloaded as part of their playlist, so they end up in the playlist's directory
+/// with the index prefix they would have had if the first attempt had worked
+struct Redownloader<'a> {
+    download_config: &'a config::DownloadConfig,
+    /// How many entries the playlist has, if it is known
+    playlist_count: Option<usize>,
+    /// Only fetched if an error doesn't say where its video is in the playlist
+    playlist_items: Option<Vec<youtube::PlaylistItem>>,
+}
+
+impl<'a> Redownloader<'a> {
+    fn new(download_config: &'a config::DownloadConfig, playlist_count: Option<usize>) -> Redownloader<'a> {
+        Redownloader { download_config, playlist_count, playlist_items: None }
+    }
+
+    /// Returns the command which downloads the video `error` refers to
+    fn command_for(&mut self, error: &YtdlpError) -> BlobResult<(Command, CurrentItem)> {
+        self.command_for_entry(error.video_id(), error.playlist_index())
+    }
+
+    /// Like command_for(), but the video is downloaded with `download_config` instead of the original preferences
+    fn command_with(&mut self, download_config: &config::DownloadConfig, error: &YtdlpError) -> BlobResult<(Command, CurrentItem)> {
+        self.build(download_config, error.video_id(), error.playlist_index())
+    }
+
+    /// Returns the command which downloads the video `video_id`, which is at `index` in the playlist if it is known
+    fn command_for_entry(&mut self, video_id: &str, index: Option<usize>) -> BlobResult<(Command, CurrentItem)> {
+        self.build(self.download_config, video_id, index)
+    }
+
+    fn build(&mut self, download_config: &config::DownloadConfig, video_id: &str, mut index: Option<usize>) -> BlobResult<(Command, CurrentItem)> {
+        let mut count = self.playlist_count;
+
+        if download_config.download_target == Some(DownloadOption::YtPlaylist) && (index.is_none() || count.is_none()) {
+            let items = self.playlist_items()?;
+            index = index.or_else(|| items.iter().find(|item| item.id == *video_id).map(|item| item.playlist_index));
+            count = count.or_else(|| items.iter().map(|item| item.playlist_index).max());
+        }
+
+        let entry = match (&download_config.download_target, index, count) {
+            (Some(DownloadOption::YtPlaylist), Some(index), Some(count)) => Some((index, count)),
+            // Single videos, and videos which can't be found in the playlist anymore
+            _ => None,
+        };
+        let command = match entry {
+            Some((index, count)) => download_config.build_command_for_playlist_item(index, count)?,
+            None => download_config.build_command_for_video(video_id)?,
+        };
+
+        Ok((command, CurrentItem {
+            video_id: Some(video_id.to_string()),
+            playlist_index: index,
+            playlist_count: count,
+            single_entry: entry.is_some(),
+        }))
+    }
+
+    fn playlist_items(&mut self) -> BlobResult<&Vec<youtube::PlaylistItem>> {
+        if self.playlist_items.is_none() {
+            let url = self.download_config.url.as_ref().ok_or(BlobdlError::UrlNotProvided)?;
+            self.playlist_items = Some(youtube::get_playlist_items(url, true)?);
+        }
+        Ok(self.playlist_items.get_or_insert_with(Vec::new))
+    }
+}
+
+/// A yt-dlp process started by run_commands()
+struct RunningProcess {
+    child: Child,
+    /// How many of its output streams haven't been closed yet
+    open_streams: usize,
+    /// Errors are attributed to whatever yt-dlp was working on when they occurred
+    current_item: CurrentItem,
+    started: Instant,
+    /// When yt-dlp last printed something
+    last_progress: Instant,
+    /// The last file finished downloading and nothing else started yet, so ffmpeg is probably working on it
+    postprocessing: bool,
+    /// blob-dl killed the process because it was taking too long
+    stopped: bool,
+    /// The last video an ItemResolved event was emitted for
+    resolved: Option<String>,
+}
+
+impl RunningProcess {
+    /// Kills yt-dlp and stops waiting for its output
+    fn kill(&mut self, id: usize, sender: &mpsc::Sender<(usize, OutputLine)>) {
+        let _ = self.child.kill();
+        // Processes started by yt-dlp (such as ffmpeg) can keep its output open after it exits
+        if self.child.wait().is_ok() && self.open_streams > 0 {
+            // The process is treated as done once this message arrives
+            self.open_streams = 1;
+            let _ = sender.send((id, OutputLine::Closed));
+        }
+    }
+}
+
+/// Runs the commands, at most `jobs` of them at the same time, and displays their merged output to the console.
+///
+/// Each command comes with what is already known about the video it downloads, if it only downloads one.
+/// `item_count` is how many videos will be downloaded in total, if it is known in advance.
 ///
-/// If yt-dlp runs into any errors, they are returned in a vector of Ytdlp errors (parsed Strings)
-fn run_command(command: &mut Command, verbosity: &parser::Verbosity) -> Option<Vec<YtdlpError>> {
-    // Run the command and capture its output
-    let mut youtube_dl = command.stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .expect("Failed to start yt-dlp process"); // TODO Should take away this expect in a future release
+/// If yt-dlp runs into any errors, they are returned in a vector of Ytdlp errors (parsed Strings),
+/// along with the information about every file that was downloaded.
+/// If a `job` is passed, it is updated every time a process finishes or an error occurs.
+/// Processes which break the limits in `advanced.supervision` are killed, and their video counts as failed.
+/// Files are picked up from the download logs while yt-dlp is running, so the on_file_complete hook is run
+/// (in the background) as soon as yt-dlp is done with a file
+fn run_commands(commands: Vec<(Command, CurrentItem)>,
+                jobs: usize,
+                item_count: Option<usize>,
+                verbosity: &parser::Verbosity,
+                advanced: &config::AdvancedPreferences,
+                mut job: Option<&mut Job>) -> BlobResult<CommandOutcome> {
+    // Errors are sorted with the user's rules first
+    let error_rules = classifier::compile(advanced.error_rules.as_deref())?;
+    // Ctrl-C stops the processes instead of killing blob-dl
+    let _guard = interrupt::catch();
+    // Set once the processes have been asked to stop, after this they are killed
+    let mut deadline: Option<Instant> = None;
+
+    // Draws progress bars and makes sure that other output doesn't get mixed up with them
+    let mut progress = ProgressTracker::new(verbosity);
+    if let Some(count) = item_count {
+        progress.set_item_count(count);
+    }
+
+    // Every process sends its output here, tagged with its id
+    let (sender, receiver) = mpsc::channel();
 
-    let stdout = BufReader::new(youtube_dl.stdout.take().unwrap());
-    let stderr = BufReader::new(youtube_dl.stderr.take().unwrap());
+    let mut pending = commands.into_iter().enumerate();
+    let mut running: HashMap<usize, RunningProcess> = HashMap::new();
+    // Where each process logs the files it finishes
+    let mut download_logs: Vec<DownloadLog> = vec![];
+    let mut last_poll = Instant::now();
+    // The files picked up from the download logs so far, by process id
+    let mut finished: BTreeMap<usize, Vec<DownloadedFile>> = BTreeMap::new();
+    let file_hooks = hooks::FileHooks::start(advanced.hooks.as_ref(), verbosity);
 
     // All the errors produced by yt-dlp
     let mut errors: Vec<YtdlpError> = vec![];
+    let mut spawn_error = None;
+    let mut spawned = vec![];
+    let mut playlist_count = None;
+    let mut warnings: Vec<String> = vec![];
 
-    match verbosity {
-        parser::Verbosity::Quiet   => {
-            // This has to be run or the command does nothing
-            for line in stdout.lines().chain(stderr.lines()) {
-                if let Ok(line) = line {
-                    // Keep track of errors without displaying anything
-                    if line.contains("ERROR:") {
-                        errors.push(YtdlpError::from_error_output(&line));
+    loop {
+        if interrupt::interrupted() {
+            match deadline {
+                None => {
+                    progress.println(&format!("{} Stopping yt-dlp...", "[blob-dl]".purple()));
+                    for process in running.values_mut() {
+                        interrupt::terminate(&mut process.child);
                     }
-                } else {
-                    // The current line had some problems (non UTF-8 characters, ...)
-                    // Since this is the quiet mode, don't do anything
+                    deadline = Some(Instant::now() + interrupt::GRACE_PERIOD);
                 }
-
-            }
-        },
-
-        parser::Verbosity::Default => {
-            for line in stdout.lines().chain(stderr.lines()) {
-                if let Ok(line) = line {
-                    // Filter what should be shown to the user
-                    if line.contains("[download]") {
-                        println!("{}{}", "[download]".green(), &line[10..]);
-                    } else if line.contains("Deleting existing file ") {
-                        println!("{}", line);
-                    } else if line.contains("[info]") {
-                        println!("{}{}", "[info]".cyan(), &line[6..]);
-                    } else if line.contains("[VideoConvertor]") {
-                        println!("{}{}", "[VideoConvertor]".purple(), &line[16..]);
+                // yt-dlp didn't stop in time
+                Some(time) if Instant::now() >= time => {
+                    for (id, process) in running.iter_mut() {
+                        process.kill(*id, &sender);
                     }
-                    else if line.contains("ERROR:") {
-                        errors.push(YtdlpError::from_error_output(&line));
-                        // Color error messages red
-                        println!("{}", line.red());
-                    } else if line.contains("Usage: yt-dlp [OPTIONS] URL [URL...]") {
-                        // The user messed with config files and blob-dl generated an invalid command for yt-dlp
-                        println!("{}", "blob-dl generated a yt-dlp command that wasn't valid, This was probably a result of errors present in a config file".red());
+                }
+                Some(_) => {}
+            }
+        } else if let Some(supervision) = &advanced.supervision {
+            for (id, process) in running.iter_mut().filter(|(_, process)| !process.stopped) {
+                if let Some(reason) = supervision.check(process.started, process.last_progress, process.postprocessing) {
+                    process.stopped = true;
+                    process.kill(*id, &sender);
+
+                    let error = YtdlpError::stopped(process.current_item.video_id.as_deref(), process.current_item.playlist_index, &reason);
+                    if let Some(job) = job.as_deref_mut() {
+                        let _ = job.record(&[], std::slice::from_ref(&error));
                     }
-                } else {
-                    eprintln!("{}", "This line couldn't be rendered as UTF-8".yellow());
+                    progress.println(&format!("{} {}", "ERROR:".red(), error.to_string().trim_end()));
+                    events::emit(failed_event(&error));
+                    errors.push(error);
+                }
+            }
+        }
+
+        // Keep `jobs` processes running as long as there are commands left
+        while running.len() < jobs && !interrupt::interrupted() && spawn_error.is_none() {
+            let (id, (mut command, current_item)) = match pending.next() {
+                Some(next) => next,
+                None => break,
+            };
+
+            // yt-dlp will log every file it finishes here
+            let download_log = DownloadLog::new();
+            command.arg("--print-to-file").arg(DOWNLOADED_FILE_TEMPLATE).arg(&download_log.path);
+            // Progress is reported one machine-readable line at a time instead of yt-dlp's progress bar
+            command.arg("--newline").arg("--progress-template").arg(progress::PROGRESS_TEMPLATE);
+            download_logs.push(download_log);
+
+            // Run the command and capture its output
+            let mut child = match command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+                Ok(child) => child,
+                Err(err) => {
+                    // The processes which are already running are allowed to finish
+                    spawn_error = Some(err);
+                    break;
+                }
+            };
+
+            spawned.push(history::command_line(&command));
+
+            // stdout and stderr are read at the same time, lines arrive in the order they were printed
+            let open_streams = output::read_output(&mut child, id, &sender);
+            if open_streams == 0 {
+                let _ = child.wait();
+                continue;
+            }
+            let now = Instant::now();
+            running.insert(id, RunningProcess {
+                child,
+                open_streams,
+                current_item,
+                started: now,
+                last_progress: now,
+                postprocessing: false,
+                stopped: false,
+                resolved: None,
+            });
+        }
+
+        if running.is_empty() {
+            break;
+        }
+
+        if last_poll.elapsed() >= DOWNLOAD_LOG_POLL_INTERVAL {
+            last_poll = Instant::now();
+            for id in running.keys() {
+                let files = download_logs[*id].read_new();
+                files_completed(&files, job.as_deref_mut(), file_hooks.as_ref());
+                finished.entry(*id).or_default().extend(files);
+            }
+        }
+        if let Some(file_hooks) = &file_hooks {
+            for message in file_hooks.messages() {
+                progress.println(&message);
+            }
+        }
+
+        // This has to be run or the commands do nothing, the timeout makes sure that Ctrl-C is noticed
+        let (id, line) = match receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let process = match running.get_mut(&id) {
+            Some(process) => process,
+            None => continue,
+        };
+
+        let line = match line {
+            OutputLine::Text(line) => {
+                process.last_progress = Instant::now();
+                line
+            }
+            OutputLine::NotUtf8 => {
+                progress.println(&"This line couldn't be rendered as UTF-8".yellow().to_string());
+                continue;
+            }
+            OutputLine::Closed => {
+                process.open_streams -= 1;
+                if process.open_streams == 0 {
+                    // Wait for yt-dlp to exit so that it doesn't become a zombie process
+                    let _ = process.child.wait();
+                    playlist_count = playlist_count.max(process.current_item.playlist_count);
+                    running.remove(&id);
+                    progress.end_process(id);
+
+                    let files = download_logs[id].close();
+                    files_completed(&files, job.as_deref_mut(), file_hooks.as_ref());
+                    finished.entry(id).or_default().extend(files);
+                }
+                continue;
+            }
+        };
+
+        if let Some(event) = ProgressEvent::parse(&line) {
+            process.postprocessing = event.status == progress::DownloadStatus::Finished;
+            if event.video_id.is_some() && event.video_id != process.resolved {
+                process.resolved = event.video_id.clone();
+                events::emit(event.to_resolved_event());
+            }
+            events::emit(event.to_event());
+            // yt-dlp prints NA for what it doesn't know, which doesn't make a known id or index unknown
+            if let Some(video_id) = &event.video_id {
+                process.current_item.video_id = Some(video_id.clone());
+            }
+            process.current_item.playlist_index = event.playlist_index.or(process.current_item.playlist_index);
+            progress.update(id, &event);
+        } else if line.contains("ERROR:") && interrupt::interrupted() {
+            // These are about the interruption itself ("Interrupted by user"), the videos didn't actually fail
+            progress.println(&line.yellow().to_string());
+        } else if line.contains("ERROR:") {
+            let mut error = YtdlpError::from_error_output(&line);
+            error.classify(&error_rules);
+            error.attribute_to(process.current_item.video_id.as_deref(), process.current_item.playlist_index);
+            if let Some(job) = job.as_deref_mut() {
+                let _ = job.record(&[], std::slice::from_ref(&error));
+            }
+            events::emit(failed_event(&error));
+            errors.push(error);
+            // Color error messages red
+            progress.println(&line.red().to_string());
+        } else {
+            process.current_item.update(&line);
+            if let Some(warning) = error::warning_from_output(&line) {
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
                 }
             }
-        },
-
-        parser::Verbosity::Verbose => {
-            // Print to the console everything that yt-dlp is doing
-            for line in stdout.lines().chain(stderr.lines()) {
-                if let Ok(line) = line {
-                    if line.contains("ERROR:") {
-                        errors.push(YtdlpError::from_error_output(&line));
-                        // Color error messages red
-                        println!("{}", line.red());
-                    } else {
-                        println!("{}", line);
+
+            match verbosity {
+                // Keep track of errors without displaying anything
+                parser::Verbosity::Quiet => {}
+                parser::Verbosity::Default => {
+                    if let Some(filtered) = filter_line(&line) {
+                        progress.println(&filtered);
                     }
-                } else {
-                    eprintln!("{}", "This line couldn't be rendered as UTF-8".yellow());
                 }
+                // Print to the console everything that yt-dlp is doing
+                parser::Verbosity::Verbose => progress.println(&line),
             }
         }
     }
+    if let Some(file_hooks) = file_hooks {
+        for message in file_hooks.finish() {
+            progress.println(&message);
+        }
+    }
+    progress.finish();
 
-    if errors.is_empty() {
-        None
+    // Files are listed in the order their processes were started
+    let downloaded = download_logs.iter_mut()
+        .enumerate()
+        .flat_map(|(id, download_log)| {
+            let mut files = finished.remove(&id).unwrap_or_default();
+            files.extend(download_log.close());
+            files
+        })
+        .collect();
+
+    match spawn_error {
+        Some(err) => Err(BlobdlError::YtdlpNotStarted(err)),
+        None => Ok(CommandOutcome { errors, downloaded, playlist_count, interrupted: interrupt::interrupted(), warnings, commands: spawned }),
+    }
+}
+
+/// What yt-dlp is working on, used to find out which video an error refers to
+#[derive(Debug, Default)]
+struct CurrentItem {
+    video_id: Option<String>,
+    playlist_index: Option<usize>,
+    /// The largest playlist size seen so far
+    playlist_count: Option<usize>,
+    /// The command only downloads the entry at `playlist_index`, which yt-dlp calls "item 1 of 1"
+    single_entry: bool,
+}
+
+impl CurrentItem {
+    /// Keeps track of the lines yt-dlp prints when it starts working on something new
+    fn update(&mut self, line: &str) {
+        if let Some(rest) = line.strip_prefix("[download] Downloading item ") {
+            // [download] Downloading item 3 of 87
+            let numbers: Vec<Option<usize>> = rest.split(" of ").map(|n| n.trim().parse().ok()).collect();
+            if !self.single_entry {
+                self.playlist_index = numbers.first().copied().flatten();
+                self.playlist_count = self.playlist_count.max(numbers.get(1).copied().flatten());
+            }
+            self.video_id = None;
+        } else if let Some(rest) = line.strip_prefix("[youtube] ") {
+            // [youtube] dQw4w9WgXcQ: Downloading webpage
+            if let Some((id, _)) = rest.split_once(": ") {
+                if !id.contains(char::is_whitespace) {
+                    self.video_id = Some(id.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Chooses what yt-dlp output is shown to the user with the default verbosity, and colors it
+fn filter_line(line: &str) -> Option<String> {
+    if line.contains("Usage: yt-dlp [OPTIONS] URL [URL...]") {
+        // The user messed with config files and blob-dl generated an invalid command for yt-dlp
+        return Some("blob-dl generated a yt-dlp command that wasn't valid, This was probably a result of errors present in a config file".red().to_string());
+    }
+    if line.contains("Deleting existing file ") {
+        return Some(line.to_string());
+    }
+
+    if let Some(rest) = line.strip_prefix("[download]") {
+        Some(format!("{}{}", "[download]".green(), rest))
+    } else if let Some(rest) = line.strip_prefix("[info]") {
+        Some(format!("{}{}", "[info]".cyan(), rest))
     } else {
-        Some(errors)
+        line.strip_prefix("[VideoConvertor]")
+            .map(|rest| format!("{}{}", "[VideoConvertor]".purple(), rest))
     }
 }
 
-/// Shows the user which videos could not be downloaded and returns which have to be re-downloaded based on what the user wants
-///
-/// Returns a Vec containing which errors the user wants to re-download
-fn ask_for_redownload(errors: &Vec<YtdlpError>) -> Vec<usize> {
-    let term = Term::buffered_stderr();
+/// The temporary file a yt-dlp process logs every file it finishes to (see DOWNLOADED_FILE_TEMPLATE)
+struct DownloadLog {
+    path: PathBuf,
+    /// How many bytes of it were already parsed
+    read: usize,
+}
 
-    // Initialize a lut, which contains all documented errors and whether they can be recovered from
-    let lut = init_error_msg_lut();
+impl DownloadLog {
+    fn new() -> DownloadLog {
+        let path = std::env::temp_dir().join(format!("blob-dl-{}-{}.jsonl",
+                                                     std::process::id(),
+                                                     DOWNLOAD_LOG_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        DownloadLog { path, read: 0 }
+    }
 
-    // The possible choices which will be presented to the user (all recoverable errors)
-    let mut user_options = Vec::new();
+    /// Parses the lines yt-dlp added since the last call, it may still be writing the last one so it is left for later.
+    ///
+    /// Lines which can't be parsed are skipped
+    fn read_new(&mut self) -> Vec<DownloadedFile> {
+        // yt-dlp didn't download anything yet
+        let Ok(contents) = std::fs::read(&self.path) else { return vec![] };
+        let new = contents.get(self.read..).unwrap_or_default();
+        let Some(end) = new.iter().rposition(|byte| *byte == b'\n') else { return vec![] };
+        self.read += end + 1;
 
-    let mut unrecoverable_errors = Vec::new();
+        String::from_utf8_lossy(&new[..=end]).lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
 
-    // Default options
-    user_options.push(String::from(SELECT_ALL));
-    user_options.push(String::from(SELECT_NOTHING));
+    /// Parses what is left of the log once yt-dlp is done, and deletes it
+    fn close(&mut self) -> Vec<DownloadedFile> {
+        let files = match std::fs::read(&self.path) {
+            // yt-dlp is done, so the last line is complete even without a line break
+            Ok(contents) => String::from_utf8_lossy(contents.get(self.read..).unwrap_or_default()).lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            Err(_) => vec![],
+        };
+        let _ = std::fs::remove_file(&self.path);
+        self.read = 0;
 
-    for error in errors {
-        if is_recoverable(error, &lut) {
-            // It makes sense to try a re-download
-            user_options.push(error.to_string())
-        } else {
-            // Don't bother asking to re-download the error
-            unrecoverable_errors.push(error);
-        }
+        files
     }
+}
 
-    if !unrecoverable_errors.is_empty() {
-        println!("{}", UNRECOVERABLE_ERROR_PROMPT.bold().cyan());
-        for error in unrecoverable_errors {
-            println!("   {}", error);
+/// Reports files yt-dlp is done with: emits their events, records them in the job and queues them for the on_file_complete hook
+fn files_completed(files: &[DownloadedFile], job: Option<&mut Job>, file_hooks: Option<&hooks::FileHooks>) {
+    for file in files {
+        events::emit(finished_event(file));
+        if let Some(file_hooks) = file_hooks {
+            file_hooks.file_complete(file);
         }
     }
+    if let Some(job) = job {
+        // Failing to save isn't fatal, the job is saved again at the end of the run
+        let _ = job.record(files, &[]);
+    }
+}
+
+/// The event emitted when a file is done, for --output-format json
+fn finished_event(file: &DownloadedFile) -> events::Event {
+    events::Event::ItemFinished(events::ItemFinished {
+        id: file.id.clone(),
+        title: file.title.clone(),
+        playlist_index: file.playlist_index,
+        path: file.filepath.clone(),
+        size: std::fs::metadata(&file.filepath).ok().map(|metadata| metadata.len()),
+    })
+}
 
-    if user_options.len() > 2 {
-        // If user_options has only 2 elements there aren't any videos to re-download
-        let user_selection = MultiSelect::with_theme(&ColorfulTheme::default())
-            .with_prompt(ERROR_RETRY_PROMPT)
-            .items(&user_options[..])
-            .interact_on(&term).unwrap();
+/// The event emitted when a video fails, for --output-format json
+fn failed_event(error: &YtdlpError) -> events::Event {
+    let category = error.category();
+    events::Event::ItemFailed(events::ItemFailed {
+        id: error.video_id().clone(),
+        playlist_index: error.playlist_index(),
+        category,
+        reason: category.to_string(),
+        hint: category.hint().to_string(),
+        message: error.error_msg().trim().to_string(),
+    })
+}
+
+/// Asks the user which of the failed videos to download again, and what to change for each of them.
+///
+/// Only videos which have a chance of working the second time are listed, the other ones are shown in the summary
+/// at the end of the run. Returns the videos to download again and the preferences to use for each of them
+fn ask_for_redownload<'e>(errors: &'e [YtdlpError], download_config: &config::DownloadConfig) -> Vec<(&'e YtdlpError, config::DownloadConfig)> {
+    let term = Term::buffered_stderr();
+    // Without a terminal (in scripts) there is nobody to ask, the run ends with the failures it had
+    if !term.is_term() {
+        return Vec::new();
+    }
 
-        println!("{}", DEBUG_REPORT_PROMPT);
-        return user_selection
+    let listed: Vec<(&YtdlpError, Vec<Remediation>)> = errors.iter()
+        .map(|error| (error, Remediation::suggested(error.category(), download_config)))
+        .filter(|(_, remediations)| !remediations.is_empty())
+        .collect();
+    if listed.is_empty() {
+        return Vec::new();
     }
 
-    // The user didn't choose any options so an empty Vec is returned
-    Vec::new()
+    // Selection 0 and 1 are hard-coded (select all | select nothing)
+    let mut user_options = vec![String::from(SELECT_ALL), String::from(SELECT_NOTHING)];
+    user_options.extend(listed.iter().map(|(error, _)| error.to_string()));
+
+    let user_selection = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(ERROR_RETRY_PROMPT)
+        .items(&user_options[..])
+        .interact_on(&term)
+        .unwrap_or_default();
+    println!("{}", DEBUG_REPORT_PROMPT);
+
+    let selected: Vec<&(&YtdlpError, Vec<Remediation>)> = match user_selection.first() {
+        None | Some(1) => vec![],
+        Some(0) => listed.iter().collect(),
+        // There is a 1:1 correspondence between the number in user_selection and the index of the video in listed
+        Some(_) => user_selection.iter().filter(|i| **i > 1).map(|i| &listed[i - 2]).collect(),
+    };
+
+    // Every video can be fixed in a different way
+    let mut chosen = vec![];
+    for (error, remediations) in selected {
+        let remediation = if remediations.len() == 1 {
+            remediations[0]
+        } else {
+            let descriptions: Vec<&str> = remediations.iter().map(Remediation::description).collect();
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("{} {}", REMEDIATION_PROMPT, error.video_id()))
+                .default(0)
+                .items(&descriptions)
+                .interact_on(&term)
+                .unwrap_or(descriptions.len() - 1);
+            remediations[selection]
+        };
+        chosen.push((*error, remediation));
+    }
+
+    // The cookies file is only asked for once
+    let cookies = if chosen.iter().any(|(_, remediation)| *remediation == Remediation::Cookies) {
+        ask_for_cookies(&term, download_config)
+    } else {
+        None
+    };
+
+    chosen.into_iter()
+        .map(|(error, remediation)| (error, remediation.apply(download_config, cookies.as_deref())))
+        .collect()
+}
+
+/// Asks for the path to a cookies file, the one in the config file (if there is one) is suggested
+fn ask_for_cookies(term: &Term, download_config: &config::DownloadConfig) -> Option<PathBuf> {
+    let theme = ColorfulTheme::default();
+    let mut input = Input::<String>::with_theme(&theme);
+    input.with_prompt(COOKIES_FILE_PROMPT);
+    if let Some(cookies) = &download_config.advanced.cookies {
+        input.default(cookies.to_string_lossy().to_string());
+    }
+    input.interact_text_on(term).ok().map(PathBuf::from)
 }
@@ -0,0 +1,215 @@
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use colored::Colorize;
+
+use crate::assembling::youtube::config::Hooks;
+use crate::job::{EntryStatus, Job, JobEntry};
+use crate::parser::Verbosity;
+use super::DownloadedFile;
+
+/// Runs on_file_complete for `file`.
+///
+/// It gets BLOBDL_FILE, BLOBDL_VIDEO_ID, BLOBDL_TITLE, BLOBDL_PLAYLIST and BLOBDL_PLAYLIST_INDEX (the ones which are known).
+/// Every hook returns the lines to show the user, since they are printed differently while progress bars are drawn
+fn on_file_complete(hooks: &Hooks, file: &DownloadedFile, verbosity: &Verbosity) -> Vec<String> {
+    let Some(hook) = &hooks.on_file_complete else { return vec![] };
+
+    let env = [
+        ("BLOBDL_FILE", Some(file.filepath.to_string_lossy().to_string())),
+        ("BLOBDL_VIDEO_ID", Some(file.id.clone())),
+        ("BLOBDL_TITLE", file.title.clone()),
+        ("BLOBDL_PLAYLIST", file.playlist.clone()),
+        ("BLOBDL_PLAYLIST_INDEX", file.playlist_index.map(|index| index.to_string())),
+    ];
+    run_hook("on_file_complete", hook, &env, verbosity)
+}
+
+/// Runs on_file_complete on a thread of its own, so that the downloads carry on (and aren't taken for stalled)
+/// while a slow hook is running.
+///
+/// Files are passed to the hook one at a time, in the order they were completed
+pub(super) struct FileHooks {
+    files: Option<Sender<DownloadedFile>>,
+    messages: Receiver<String>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl FileHooks {
+    /// Returns None if there is no on_file_complete hook
+    pub(super) fn start(hooks: Option<&Hooks>, verbosity: &Verbosity) -> Option<FileHooks> {
+        let hooks = hooks.filter(|hooks| hooks.on_file_complete.is_some())?.clone();
+        let verbosity = verbosity.clone();
+        let (files, queued) = mpsc::channel::<DownloadedFile>();
+        let (sender, messages) = mpsc::channel();
+
+        let worker = thread::spawn(move || {
+            for file in queued {
+                for message in on_file_complete(&hooks, &file, &verbosity) {
+                    let _ = sender.send(message);
+                }
+            }
+        });
+        Some(FileHooks { files: Some(files), messages, worker: Some(worker) })
+    }
+
+    /// Queues `file` for the hook
+    pub(super) fn file_complete(&self, file: &DownloadedFile) {
+        if let Some(files) = &self.files {
+            let _ = files.send(file.clone());
+        }
+    }
+
+    /// What the hooks which already ran want to show the user
+    pub(super) fn messages(&self) -> Vec<String> {
+        self.messages.try_iter().collect()
+    }
+
+    /// Waits for the hook to be run for every queued file and returns what is left to show the user
+    pub(super) fn finish(mut self) -> Vec<String> {
+        drop(self.files.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        self.messages()
+    }
+}
+
+/// Runs on_error for a video of the job which couldn't be downloaded.
+///
+/// It gets BLOBDL_URL, BLOBDL_VIDEO_ID, BLOBDL_TITLE, BLOBDL_PLAYLIST_INDEX, BLOBDL_ERROR and BLOBDL_ERROR_CATEGORY
+pub(super) fn on_error(hooks: &Hooks, job: &Job, entry: &JobEntry, verbosity: &Verbosity) -> Vec<String> {
+    let Some(hook) = &hooks.on_error else { return vec![] };
+
+    let env = [
+        ("BLOBDL_URL", job.state.download_config.url.clone()),
+        ("BLOBDL_VIDEO_ID", Some(entry.id.clone())),
+        ("BLOBDL_TITLE", entry.title.clone()),
+        ("BLOBDL_PLAYLIST_INDEX", entry.playlist_index.map(|index| index.to_string())),
+        ("BLOBDL_ERROR", entry.error.clone()),
+        ("BLOBDL_ERROR_CATEGORY", entry.category.map(|category| category.to_string())),
+    ];
+    run_hook("on_error", hook, &env, verbosity)
+}
+
+/// Runs on_run_complete once the run is over.
+///
+/// It gets BLOBDL_URL, BLOBDL_JOB and how many videos were downloaded, failed and skipped
+/// (BLOBDL_DOWNLOADED, BLOBDL_FAILED, BLOBDL_SKIPPED)
+pub(super) fn on_run_complete(hooks: &Hooks, job: &Job, downloaded: usize, skipped: usize, verbosity: &Verbosity) -> Vec<String> {
+    let Some(hook) = &hooks.on_run_complete else { return vec![] };
+
+    let failed = job.state.entries.iter().filter(|entry| entry.status == EntryStatus::Failed).count();
+    let env = [
+        ("BLOBDL_URL", job.state.download_config.url.clone()),
+        ("BLOBDL_JOB", Some(job.state.name.clone())),
+        ("BLOBDL_DOWNLOADED", Some(downloaded.to_string())),
+        ("BLOBDL_FAILED", Some(failed.to_string())),
+        ("BLOBDL_SKIPPED", Some(skipped.to_string())),
+    ];
+    run_hook("on_run_complete", hook, &env, verbosity)
+}
+
+/// Runs `hook` with the system's shell and waits for it.
+///
+/// Its output is only shown in verbose mode, unless it fails: then what it printed to stderr is shown anyway
+fn run_hook(name: &str, hook: &str, env: &[(&str, Option<String>)], verbosity: &Verbosity) -> Vec<String> {
+    let mut command = shell(hook);
+    command.stdin(Stdio::null());
+    for (key, value) in env {
+        if let Some(value) = value {
+            command.env(key, value);
+        }
+    }
+
+    let mut messages = vec![];
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(err) => {
+            messages.push(format!("{} The {} hook couldn't be started: {}", "[blob-dl]".purple(), name, err.to_string().red()));
+            return messages;
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if let Verbosity::Verbose = verbosity {
+        messages.extend(stdout.lines().chain(stderr.lines()).map(|line| format!("[{}] {}", name, line)));
+    }
+    if !output.status.success() {
+        messages.push(format!("{} The {} hook failed ({})", "[blob-dl]".purple(), name, output.status.to_string().red()));
+        if !matches!(verbosity, Verbosity::Verbose) {
+            messages.extend(stderr.lines().map(|line| format!("[{}] {}", name, line)));
+        }
+    }
+    messages
+}
+
+#[cfg(unix)]
+fn shell(hook: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(hook);
+    command
+}
+
+#[cfg(not(unix))]
+fn shell(hook: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").arg(hook);
+    command
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn file(id: &str) -> DownloadedFile {
+        DownloadedFile {
+            id: id.to_string(),
+            title: Some(format!("Title of {}", id)),
+            filepath: PathBuf::from(format!("/music/{}.mp3", id)),
+            duration: None,
+            playlist: Some("Album".to_string()),
+            playlist_index: None,
+            description: None,
+            sponsorblock_chapters: None,
+            format_id: None,
+            height: None,
+            vcodec: None,
+            acodec: None,
+        }
+    }
+
+    #[test]
+    fn file_hooks_get_the_file_and_survive_failures() {
+        let log = std::env::temp_dir().join(format!("blob-dl-hooks-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&log);
+        let hooks = Hooks {
+            // The first file makes the hook fail, the second one has to be handled anyway
+            on_file_complete: Some(format!(
+                "echo \"$BLOBDL_VIDEO_ID|$BLOBDL_FILE|$BLOBDL_TITLE|$BLOBDL_PLAYLIST|${{BLOBDL_PLAYLIST_INDEX-unset}}\" >> '{}'; \
+                 [ \"$BLOBDL_VIDEO_ID\" != first ] || {{ echo broken >&2; exit 3; }}",
+                log.display())),
+            ..Hooks::default()
+        };
+
+        let file_hooks = FileHooks::start(Some(&hooks), &Verbosity::Default).unwrap();
+        file_hooks.file_complete(&file("first"));
+        file_hooks.file_complete(&file("second"));
+        let messages = file_hooks.finish();
+
+        assert_eq!(std::fs::read_to_string(&log).unwrap(),
+                   "first|/music/first.mp3|Title of first|Album|unset\nsecond|/music/second.mp3|Title of second|Album|unset\n");
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].contains("The on_file_complete hook failed"), "{:?}", messages);
+        assert_eq!(messages[1], "[on_file_complete] broken");
+        let _ = std::fs::remove_file(&log);
+    }
+
+    #[test]
+    fn no_hook_no_worker() {
+        assert!(FileHooks::start(None, &Verbosity::Default).is_none());
+        assert!(FileHooks::start(Some(&Hooks::default()), &Verbosity::Default).is_none());
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::Path;
+use std::process::Child;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long yt-dlp is given to stop on its own after Ctrl-C, before it is killed
+pub(super) const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// yt-dlp leaves these behind when it is stopped in the middle of a download
+const FRAGMENT_EXTENSIONS: [&str; 2] = ["part", "ytdl"];
+
+/// Set when the user presses Ctrl-C (or blob-dl is asked to terminate) during a download
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// How many Guards are alive, Ctrl-C is only caught while downloads are running
+static CATCHING: AtomicUsize = AtomicUsize::new(0);
+
+static INSTALL_HANDLER: Once = Once::new();
+
+/// While a Guard is alive Ctrl-C doesn't kill blob-dl, it only marks the run as interrupted.
+///
+/// Pressing it a second time still quits immediately
+pub(super) struct Guard;
+
+pub(super) fn catch() -> Guard {
+    INSTALL_HANDLER.call_once(|| {
+        let _ = ctrlc::set_handler(|| {
+            if CATCHING.load(Ordering::SeqCst) == 0 || INTERRUPTED.swap(true, Ordering::SeqCst) {
+                std::process::exit(130);
+            }
+        });
+    });
+    CATCHING.fetch_add(1, Ordering::SeqCst);
+    Guard
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        CATCHING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub(crate) fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Asks yt-dlp to stop, which gives it a chance to clean up after itself
+pub(super) fn terminate(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        // SAFETY: kill() only sends a signal, the pid belongs to a child which hasn't been waited yet
+        unsafe {
+            libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = child.kill();
+    }
+}
+
+/// Deletes the fragments yt-dlp left in `directory` (or in the directories right inside it, where playlists go)
+/// since `since`, returns how many were deleted
+pub(super) fn remove_fragments(directory: &Path, since: SystemTime) -> usize {
+    remove_fragments_in(directory, since, 1)
+}
+
+fn remove_fragments_in(directory: &Path, since: SystemTime, depth: usize) -> usize {
+    let mut removed = 0;
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 0 {
+                removed += remove_fragments_in(&path, since, depth - 1);
+            }
+            continue;
+        }
+
+        // Fragments of fragmented downloads are called like `video.mp4.part-Frag12`
+        let is_fragment = path.file_name()
+            .map(|name| name.to_string_lossy())
+            .is_some_and(|name| FRAGMENT_EXTENSIONS.iter().any(|ext| name.ends_with(&format!(".{}", ext)) || name.contains(&format!(".{}-Frag", ext))));
+        let is_new = entry.metadata().and_then(|metadata| metadata.modified()).is_ok_and(|modified| modified >= since);
+
+        if is_fragment && is_new && std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// Like thread::sleep(), but returns early if the run is interrupted
+pub(super) fn sleep(duration: Duration) {
+    let start = Instant::now();
+    while !interrupted() && start.elapsed() < duration {
+        std::thread::sleep(duration.saturating_sub(start.elapsed()).min(Duration::from_millis(100)));
+    }
+}
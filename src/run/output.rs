@@ -0,0 +1,60 @@
+use std::io::{BufRead, BufReader, Read};
+use std::process::Child;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+/// Something a yt-dlp process printed, in the order it was received
+#[derive(Debug)]
+pub(crate) enum OutputLine {
+    Text(String),
+    /// The line had some problems (non UTF-8 characters, ...)
+    NotUtf8,
+    /// One of the process' streams was closed, once both are closed the process is done
+    Closed,
+}
+
+/// Reads a yt-dlp process' stdout and stderr at the same time, each on its own thread, and sends every line
+/// to `sender` tagged with `process` (the id of the process, several of them can share the same channel).
+///
+/// Reading the streams one after the other would make yt-dlp block forever as soon as the pipe which isn't being read fills up.
+/// Returns how many streams are being read, OutputLine::Closed is sent once for each of them
+pub(crate) fn read_output(child: &mut Child, process: usize, sender: &Sender<(usize, OutputLine)>) -> usize {
+    let mut streams = 0;
+
+    if let Some(stdout) = child.stdout.take() {
+        spawn_reader(stdout, process, sender.clone());
+        streams += 1;
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_reader(stderr, process, sender.clone());
+        streams += 1;
+    }
+
+    streams
+}
+
+fn spawn_reader<R: Read + Send + 'static>(stream: R, process: usize, sender: Sender<(usize, OutputLine)>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut buffer = vec![];
+
+        loop {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer) {
+                // The stream was closed
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let line = match std::str::from_utf8(&buffer) {
+                        Ok(line) => OutputLine::Text(line.trim_end_matches(['\n', '\r']).to_string()),
+                        Err(_) => OutputLine::NotUtf8,
+                    };
+                    if sender.send((process, line)).is_err() {
+                        // Nobody is listening anymore
+                        return;
+                    }
+                }
+            }
+        }
+        let _ = sender.send((process, OutputLine::Closed));
+    });
+}
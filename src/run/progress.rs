@@ -0,0 +1,423 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+use colored::Colorize;
+use dialoguer::console::{truncate_str, Term};
+
+use crate::events;
+use crate::parser;
+use crate::tracklist;
+
+/// yt-dlp prints a line formatted like this (with --newline, one per update) instead of its usual progress bar.
+///
+/// Fields are separated by '|', the title goes last because it could contain '|' itself
+pub(crate) const PROGRESS_TEMPLATE: &str = "download:BLOBDL_PROGRESS|%(progress.status)s|%(info.id)s|%(info.playlist_index)s|%(info.n_entries)s|%(progress.downloaded_bytes)s|%(progress.total_bytes)s|%(progress.total_bytes_estimate)s|%(progress.speed)s|%(progress.eta)s|%(info.title)s";
+
+const PROGRESS_PREFIX: &str = "BLOBDL_PROGRESS|";
+
+/// How many characters wide progress bars are
+const BAR_WIDTH: usize = 25;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DownloadStatus {
+    Downloading,
+    /// A file (a video stream, an audio stream or a whole video) finished downloading
+    Finished,
+    Error,
+}
+
+/// A progress update, parsed from a line printed with PROGRESS_TEMPLATE
+#[derive(Debug, Clone)]
+pub(crate) struct ProgressEvent {
+    pub(crate) status: DownloadStatus,
+    pub(crate) video_id: Option<String>,
+    pub(crate) title: Option<String>,
+    /// Only present when downloading a playlist
+    pub(crate) playlist_index: Option<usize>,
+    pub(crate) playlist_count: Option<usize>,
+    pub(crate) downloaded_bytes: Option<u64>,
+    /// The exact size if yt-dlp knows it, otherwise its estimate
+    pub(crate) total_bytes: Option<u64>,
+    /// Bytes per second
+    pub(crate) speed: Option<f64>,
+    /// Seconds
+    pub(crate) eta: Option<u64>,
+}
+
+impl ProgressEvent {
+    /// Returns None if `line` isn't a progress line
+    pub(crate) fn parse(line: &str) -> Option<ProgressEvent> {
+        let fields: Vec<&str> = line.trim().strip_prefix(PROGRESS_PREFIX)?.splitn(10, '|').collect();
+        if fields.len() < 10 {
+            return None;
+        }
+
+        let status = match fields[0] {
+            "downloading" => DownloadStatus::Downloading,
+            "finished" => DownloadStatus::Finished,
+            "error" => DownloadStatus::Error,
+            _ => return None,
+        };
+
+        Some(ProgressEvent {
+            status,
+            video_id: text_field(fields[1]),
+            playlist_index: number_field(fields[2]).map(|n| n as usize),
+            playlist_count: number_field(fields[3]).map(|n| n as usize),
+            downloaded_bytes: number_field(fields[4]).map(|n| n as u64),
+            total_bytes: number_field(fields[5]).or_else(|| number_field(fields[6])).map(|n| n as u64),
+            speed: number_field(fields[7]),
+            eta: number_field(fields[8]).map(|n| n as u64),
+            title: text_field(fields[9]),
+        })
+    }
+}
+
+impl ProgressEvent {
+    /// The update as it is emitted with --output-format json
+    pub(crate) fn to_event(&self) -> events::Event {
+        events::Event::Progress(events::Progress {
+            id: self.video_id.clone(),
+            playlist_index: self.playlist_index,
+            status: match self.status {
+                DownloadStatus::Downloading => events::ProgressStatus::Downloading,
+                DownloadStatus::Finished => events::ProgressStatus::Finished,
+                DownloadStatus::Error => events::ProgressStatus::Error,
+            },
+            downloaded_bytes: self.downloaded_bytes,
+            total_bytes: self.total_bytes,
+            speed: self.speed,
+            eta: self.eta,
+        })
+    }
+
+    /// Emitted the first time an update about a video arrives
+    pub(crate) fn to_resolved_event(&self) -> events::Event {
+        events::Event::ItemResolved(events::ItemResolved {
+            id: self.video_id.clone().unwrap_or_default(),
+            title: self.title.clone(),
+            playlist_index: self.playlist_index,
+        })
+    }
+}
+
+/// yt-dlp writes NA when a field isn't available
+fn text_field(field: &str) -> Option<String> {
+    match field {
+        "NA" | "None" | "" => None,
+        _ => Some(field.to_string()),
+    }
+}
+
+fn number_field(field: &str) -> Option<f64> {
+    field.parse::<f64>().ok().filter(|n| n.is_finite() && *n >= 0.0)
+}
+
+/// The progress of the item a yt-dlp process is currently downloading
+#[derive(Debug)]
+struct ItemProgress {
+    video_id: Option<String>,
+    title: Option<String>,
+    /// Bytes of the files of this item which have already been downloaded completely
+    /// (a video can be made of more than one file, like a video stream and an audio stream)
+    finished_bytes: u64,
+    downloaded: u64,
+    total: Option<u64>,
+    speed: Option<f64>,
+    eta: Option<u64>,
+}
+
+/// Keeps track of the progress of a whole run and draws a bar for every item which is being downloaded
+/// plus (when downloading a playlist) an overall bar.
+///
+/// More than one yt-dlp process can be followed at the same time, each is identified by a number.
+/// Any other output has to go through println() so that it doesn't get mixed up with the bars
+pub(crate) struct ProgressTracker {
+    term: Term,
+    /// Bars are only drawn on interactive terminals, never in quiet mode and never with --output-format json
+    draw_bars: bool,
+    quiet: bool,
+    /// How many lines the bars currently take on screen
+    drawn_lines: usize,
+
+    /// What every process is downloading right now
+    items: BTreeMap<usize, ItemProgress>,
+    /// How many items the playlist has, if a playlist is being downloaded
+    item_count: Option<usize>,
+    /// How many different items progress was reported for
+    items_started: usize,
+    /// Bytes of all the files which have already been downloaded completely
+    finished_bytes: u64,
+    started: Instant,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(verbosity: &parser::Verbosity) -> ProgressTracker {
+        let term = Term::stdout();
+        let quiet = matches!(verbosity, parser::Verbosity::Quiet);
+
+        ProgressTracker {
+            draw_bars: !quiet && !events::enabled() && term.is_term(),
+            term,
+            quiet,
+            drawn_lines: 0,
+            items: BTreeMap::new(),
+            item_count: None,
+            items_started: 0,
+            finished_bytes: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Used when the size of the playlist is known in advance
+    pub(crate) fn set_item_count(&mut self, count: usize) {
+        self.item_count = Some(count);
+    }
+
+    /// Records a progress update coming from `process`
+    pub(crate) fn update(&mut self, process: usize, event: &ProgressEvent) {
+        let is_new_item = self.items.get(&process)
+            .is_none_or(|item| item.video_id != event.video_id);
+
+        if is_new_item {
+            self.items_started += 1;
+            self.items.insert(process, ItemProgress {
+                video_id: event.video_id.clone(),
+                title: None,
+                finished_bytes: 0,
+                downloaded: 0,
+                total: None,
+                speed: None,
+                eta: None,
+            });
+        }
+        if self.item_count.is_none() {
+            self.item_count = event.playlist_count;
+        }
+
+        let item = match self.items.get_mut(&process) {
+            Some(item) => item,
+            None => return,
+        };
+        item.title = event.title.clone();
+        item.speed = event.speed;
+        item.eta = event.eta;
+
+        match event.status {
+            DownloadStatus::Downloading => {
+                item.downloaded = event.downloaded_bytes.unwrap_or(0);
+                item.total = event.total_bytes;
+            }
+            DownloadStatus::Finished => {
+                let size = event.total_bytes.or(event.downloaded_bytes).unwrap_or(item.downloaded);
+                self.finished_bytes += size;
+                item.finished_bytes += size;
+                item.downloaded = 0;
+                item.total = None;
+
+                // Bars disappear, so leave a line saying that the file is done
+                let line = format!("{} Downloaded {} ({})",
+                                   "[download]".green(),
+                                   item.title.as_deref().unwrap_or("a file"),
+                                   format_bytes(item.finished_bytes));
+                self.println(&line);
+                return;
+            }
+            DownloadStatus::Error => {
+                item.downloaded = 0;
+                item.total = None;
+            }
+        }
+        self.redraw();
+    }
+
+    /// The process exited, so its bar isn't needed anymore
+    pub(crate) fn end_process(&mut self, process: usize) {
+        self.items.remove(&process);
+        self.redraw();
+    }
+
+    /// Prints a line above the progress bars
+    pub(crate) fn println(&mut self, line: &str) {
+        if self.quiet {
+            return;
+        }
+        self.clear();
+        // stdout is reserved for events
+        if events::enabled() {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+        self.redraw();
+    }
+
+    /// Removes the bars from the screen, call this when the download is over
+    pub(crate) fn finish(&mut self) {
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        if self.drawn_lines > 0 {
+            let _ = self.term.clear_last_lines(self.drawn_lines);
+            self.drawn_lines = 0;
+        }
+    }
+
+    fn redraw(&mut self) {
+        if !self.draw_bars {
+            return;
+        }
+        self.clear();
+        if self.items.is_empty() {
+            return;
+        }
+
+        let width = self.term.size().1 as usize;
+        let mut lines: Vec<String> = self.items.values().map(item_bar).collect();
+        if let Some(overall) = self.overall_bar() {
+            lines.push(overall);
+        }
+
+        for line in &lines {
+            // Lines that wrap around would break clear_last_lines()
+            let _ = self.term.write_line(&truncate_str(line, width.saturating_sub(1), "…"));
+        }
+        self.drawn_lines = lines.len();
+    }
+
+    /// `[overall] video 12/87, 3.40 GB of 9.10 GB, ETA 12:03`
+    ///
+    /// Only available for playlists. The total size is an estimate: items which haven't started yet
+    /// are assumed to be as big as the average item so far
+    fn overall_bar(&self) -> Option<String> {
+        let count = self.item_count?;
+        let started = self.items_started.max(1);
+
+        let current_downloaded: u64 = self.items.values().map(|item| item.downloaded).sum();
+        let current_total: u64 = self.items.values().map(|item| item.total.unwrap_or(item.downloaded)).sum();
+
+        let downloaded = self.finished_bytes + current_downloaded;
+        let known_total = self.finished_bytes + current_total;
+        let average = known_total / started as u64;
+        let estimated_total = known_total + average * count.saturating_sub(started) as u64;
+
+        let mut line = format!("{} video {}/{}, {} of {}",
+                               "[overall]".cyan(),
+                               started.min(count),
+                               count,
+                               format_bytes(downloaded),
+                               format_bytes(estimated_total));
+
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed > 1.0 && downloaded > 0 {
+            let average_speed = downloaded as f64 / elapsed;
+            let eta = estimated_total.saturating_sub(downloaded) as f64 / average_speed;
+            line.push_str(&format!(", ETA {}", tracklist::format_timestamp(eta as u64)));
+        }
+
+        Some(line)
+    }
+}
+
+/// `[download]  45% [#########...] 12.30 MB of 27.10 MB at 2.30 MB/s ETA 0:07 | title`
+fn item_bar(item: &ItemProgress) -> String {
+    let mut line = format!("{} ", "[download]".green());
+
+    match item.total {
+        Some(total) if total > 0 => {
+            let ratio = (item.downloaded as f64 / total as f64).min(1.0);
+            line.push_str(&format!("{:>3.0}% {} {} of {}", ratio * 100.0, bar(ratio), format_bytes(item.downloaded), format_bytes(total)));
+        }
+        _ => line.push_str(&format!("{} so far", format_bytes(item.downloaded))),
+    }
+    if let Some(speed) = item.speed {
+        line.push_str(&format!(" at {}/s", format_bytes(speed as u64)));
+    }
+    if let Some(eta) = item.eta {
+        line.push_str(&format!(" ETA {}", tracklist::format_timestamp(eta)));
+    }
+    if let Some(title) = &item.title {
+        line.push_str(&format!(" | {}", title));
+    }
+    line
+}
+
+/// `[#########................]`
+fn bar(ratio: f64) -> String {
+    let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+    format!("[{}{}]", "#".repeat(filled), ".".repeat(BAR_WIDTH - filled))
+}
+
+/// Formats a size in bytes using the most appropriate unit (decimal, like yt-dlp's estimates on youtube)
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    let bytes = bytes as f64;
+    if bytes >= 1e9 {
+        format!("{:.2} GB", bytes / 1e9)
+    } else if bytes >= 1e6 {
+        format!("{:.2} MB", bytes / 1e6)
+    } else if bytes >= 1e3 {
+        format!("{:.2} KB", bytes / 1e3)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downloading_lines() {
+        let event = ProgressEvent::parse("BLOBDL_PROGRESS|downloading|dQw4w9WgXcQ|3|12|1024|4096|NA|512.5|6|Title").unwrap();
+        assert_eq!(event.status, DownloadStatus::Downloading);
+        assert_eq!(event.video_id.as_deref(), Some("dQw4w9WgXcQ"));
+        assert_eq!(event.playlist_index, Some(3));
+        assert_eq!(event.playlist_count, Some(12));
+        assert_eq!(event.downloaded_bytes, Some(1024));
+        assert_eq!(event.total_bytes, Some(4096));
+        assert_eq!(event.speed, Some(512.5));
+        assert_eq!(event.eta, Some(6));
+        assert_eq!(event.title.as_deref(), Some("Title"));
+    }
+
+    #[test]
+    fn finished_lines() {
+        let event = ProgressEvent::parse("BLOBDL_PROGRESS|finished|dQw4w9WgXcQ|NA|NA|4096|4096|NA|NA|NA|Title").unwrap();
+        assert_eq!(event.status, DownloadStatus::Finished);
+        assert_eq!(event.downloaded_bytes, Some(4096));
+        assert_eq!(event.eta, None);
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let event = ProgressEvent::parse("BLOBDL_PROGRESS|downloading|NA|NA|NA|NA|NA|NA|NA|NA|NA").unwrap();
+        assert_eq!(event.video_id, None);
+        assert_eq!(event.playlist_index, None);
+        assert_eq!(event.playlist_count, None);
+        assert_eq!(event.downloaded_bytes, None);
+        assert_eq!(event.total_bytes, None);
+        assert_eq!(event.speed, None);
+        assert_eq!(event.eta, None);
+        assert_eq!(event.title, None);
+
+        // The estimate is used when the exact size isn't known
+        let event = ProgressEvent::parse("BLOBDL_PROGRESS|downloading|id|NA|NA|10|NA|2048.0|NA|NA|Title").unwrap();
+        assert_eq!(event.total_bytes, Some(2048));
+    }
+
+    #[test]
+    fn titles_with_separators() {
+        let event = ProgressEvent::parse("BLOBDL_PROGRESS|downloading|id|1|2|10|20|NA|5|2|Artist | Song | Live").unwrap();
+        assert_eq!(event.title.as_deref(), Some("Artist | Song | Live"));
+        assert_eq!(event.eta, Some(2));
+    }
+
+    #[test]
+    fn other_lines() {
+        assert!(ProgressEvent::parse("[download] Downloading item 3 of 12").is_none());
+        assert!(ProgressEvent::parse("BLOBDL_PROGRESS|downloading|id|1|2|10").is_none());
+        assert!(ProgressEvent::parse("BLOBDL_PROGRESS|paused|id|1|2|10|20|NA|5|2|Title").is_none());
+        assert!(ProgressEvent::parse("BLOBDL_PROGRESS|").is_none());
+        assert!(ProgressEvent::parse("").is_none());
+    }
+}
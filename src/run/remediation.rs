@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use crate::assembling::youtube::VideoQualityAndFormatPreferences;
+use crate::assembling::youtube::config::DownloadConfig;
+use crate::error::ErrorCategory;
+
+/// Something that can be changed before downloading a failed video again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Remediation {
+    /// Same preferences as the first attempt
+    Retry,
+    /// The chosen format isn't available for this video, use the best one it has
+    BestFormat,
+    /// Let yt-dlp log in with a cookies file
+    Cookies,
+    /// Keep the file as it is downloaded instead of converting it with ffmpeg
+    NoRecode,
+}
+
+impl Remediation {
+    /// What can be done about a video which failed with an error of `category`, the most useful action first.
+    ///
+    /// Actions which wouldn't change anything with `download_config` aren't suggested,
+    /// an empty Vec means that downloading the video again is pointless
+    pub(super) fn suggested(category: ErrorCategory, download_config: &DownloadConfig) -> Vec<Remediation> {
+        let mut remediations = match category {
+            ErrorCategory::FormatUnavailable if !matches!(download_config.chosen_format, Some(VideoQualityAndFormatPreferences::BestQuality)) => {
+                vec![Remediation::BestFormat]
+            }
+            ErrorCategory::Private | ErrorCategory::AgeRestricted | ErrorCategory::MembersOnly => vec![Remediation::Cookies],
+            ErrorCategory::Postprocessing if recodes(download_config) => vec![Remediation::NoRecode],
+            _ => vec![],
+        };
+        if !remediations.is_empty() || category.retry_by_default() {
+            remediations.push(Remediation::Retry);
+        }
+        remediations
+    }
+
+    /// Shown in the retry menu
+    pub(super) fn description(&self) -> &'static str {
+        match self {
+            Remediation::Retry => "Download it again",
+            Remediation::BestFormat => "Download the best format available instead",
+            Remediation::Cookies => "Download it again using a cookies file",
+            Remediation::NoRecode => "Download it again without converting it",
+        }
+    }
+
+    /// The preferences to download the video again with, `cookies` is the file chosen by the user for Remediation::Cookies.
+    ///
+    /// If the user didn't choose one (they cancelled the prompt) the cookies file in `download_config` is kept
+    pub(super) fn apply(&self, download_config: &DownloadConfig, cookies: Option<&Path>) -> DownloadConfig {
+        let mut download_config = download_config.clone();
+        match self {
+            Remediation::Retry => {}
+            Remediation::BestFormat => download_config.chosen_format = Some(VideoQualityAndFormatPreferences::BestQuality),
+            Remediation::Cookies => {
+                if let Some(cookies) = cookies {
+                    download_config.advanced.cookies = Some(cookies.to_path_buf());
+                }
+            }
+            Remediation::NoRecode => {
+                if let Some(VideoQualityAndFormatPreferences::ConvertTo(_)) = download_config.chosen_format {
+                    download_config.chosen_format = Some(VideoQualityAndFormatPreferences::BestQuality);
+                }
+                // Precise cuts are made by re-encoding the video
+                download_config.force_keyframes_at_cuts = Some(false);
+            }
+        }
+        download_config
+    }
+}
+
+/// Whether ffmpeg has to re-encode what yt-dlp downloads
+fn recodes(download_config: &DownloadConfig) -> bool {
+    matches!(download_config.chosen_format, Some(VideoQualityAndFormatPreferences::ConvertTo(_)))
+        || download_config.force_keyframes_at_cuts == Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn cookies_are_kept_without_a_new_file() {
+        let mut download_config = DownloadConfig::empty();
+        download_config.advanced.cookies = Some(PathBuf::from("config-cookies.txt"));
+
+        let kept = Remediation::Cookies.apply(&download_config, None);
+        assert_eq!(kept.advanced.cookies, Some(PathBuf::from("config-cookies.txt")));
+        let replaced = Remediation::Cookies.apply(&download_config, Some(Path::new("new-cookies.txt")));
+        assert_eq!(replaced.advanced.cookies, Some(PathBuf::from("new-cookies.txt")));
+    }
+}
@@ -0,0 +1,128 @@
+use std::process::Command;
+use colored::Colorize;
+
+use crate::parser;
+use crate::job::Job;
+use crate::error::{BlobResult, YtdlpError};
+use crate::assembling::youtube::config::RetryPolicy;
+use super::{interrupt, run_commands, CommandOutcome, CurrentItem, Redownloader};
+
+/// What happened to a video which failed to download at least once
+#[derive(Debug)]
+enum RetryStatus {
+    /// It will be retried in the next round
+    Pending,
+    Recovered,
+    /// Every attempt failed
+    GaveUp,
+    /// The error isn't one the policy retries
+    NotRetried,
+}
+
+#[derive(Debug)]
+struct FailedVideo {
+    /// The last error this video ran into
+    error: YtdlpError,
+    /// How many times it was downloaded, the first attempt included
+    attempts: u32,
+    status: RetryStatus,
+}
+
+/// Keeps retrying the videos in `errors` according to `policy`, without asking the user anything.
+///
+/// After every round the results are checked again, until every failure is either resolved or out of attempts.
+/// Prints what happened to every video at the end and returns what the retries did: the files they downloaded,
+/// the last error of every video which couldn't be recovered, the warnings yt-dlp printed and the commands which were run
+pub(super) fn retry_failed(errors: Vec<YtdlpError>,
+                           policy: &RetryPolicy,
+                           redownloader: &mut Redownloader,
+                           jobs: usize,
+                           verbosity: &parser::Verbosity,
+                           job: &mut Job) -> BlobResult<CommandOutcome> {
+    let mut failed: Vec<FailedVideo> = vec![];
+    // yt-dlp can print more than one error for the same video, the last one is kept
+    for error in errors {
+        match failed.iter_mut().find(|video| video.error.video_id() == error.video_id()) {
+            Some(video) => video.error = error,
+            None => failed.push(FailedVideo { error, attempts: 1, status: RetryStatus::Pending }),
+        }
+    }
+
+    let mut downloaded = vec![];
+    let mut warnings = vec![];
+    let mut commands_run = vec![];
+    let mut round = 1;
+
+    loop {
+        for video in failed.iter_mut().filter(|video| matches!(video.status, RetryStatus::Pending)) {
+            // Without an id there is no way to download the video again
+            if !policy.retries(video.error.category()) || video.error.video_id() == "unavailable" {
+                video.status = RetryStatus::NotRetried;
+            } else if video.attempts >= policy.max_attempts {
+                video.status = RetryStatus::GaveUp;
+            }
+        }
+
+        let pending: Vec<&mut FailedVideo> = failed.iter_mut()
+            .filter(|video| matches!(video.status, RetryStatus::Pending))
+            .collect();
+        if pending.is_empty() || interrupt::interrupted() {
+            break;
+        }
+
+        let delay = policy.delay(round);
+        println!("{} Retrying {} video(s) in {:.1}s (round {} of {})",
+                 "[blob-dl]".purple(),
+                 pending.len(),
+                 delay.as_secs_f64(),
+                 round,
+                 policy.max_attempts.saturating_sub(1));
+        interrupt::sleep(delay);
+        if interrupt::interrupted() {
+            break;
+        }
+
+        let mut commands: Vec<(Command, CurrentItem)> = vec![];
+        for video in &pending {
+            commands.push(redownloader.command_for(&video.error)?);
+        }
+
+        let outcome = run_commands(commands, jobs, None, verbosity, &redownloader.download_config.advanced, Some(&mut *job))?;
+        downloaded.extend(outcome.downloaded);
+        warnings.extend(outcome.warnings);
+        commands_run.extend(outcome.commands);
+
+        for video in pending {
+            video.attempts += 1;
+            match outcome.errors.iter().rfind(|error| error.video_id() == video.error.video_id()) {
+                Some(error) => video.error = error.clone(),
+                None => video.status = RetryStatus::Recovered,
+            }
+        }
+        round += 1;
+    }
+
+    print_outcomes(&failed);
+    let errors = failed.into_iter()
+        .filter(|video| !matches!(video.status, RetryStatus::Recovered))
+        .map(|video| video.error)
+        .collect();
+    Ok(CommandOutcome { errors, downloaded, playlist_count: None, interrupted: interrupt::interrupted(), warnings, commands: commands_run })
+}
+
+/// Tells the user what happened to every video that failed at least once
+fn print_outcomes(failed: &[FailedVideo]) {
+    if failed.is_empty() {
+        return;
+    }
+    println!("{}", "Videos which ran into errors:".bold().cyan());
+
+    for video in failed {
+        let outcome = match video.status {
+            RetryStatus::Recovered => format!("downloaded after {} attempts", video.attempts).green(),
+            RetryStatus::GaveUp => format!("failed after {} attempts ({})", video.attempts, video.error.category()).red(),
+            RetryStatus::NotRetried | RetryStatus::Pending => format!("not retried ({})", video.error.category()).yellow(),
+        };
+        println!("   {}: {}", outcome, video.error.to_string().trim_end());
+    }
+}
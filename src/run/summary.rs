@@ -0,0 +1,453 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use colored::Colorize;
+use serde::Serialize;
+use which::which;
+
+use crate::assembling::youtube::{SponsorBlockPreferences, VideoQualityAndFormatPreferences};
+use crate::assembling::youtube::config::DownloadConfig;
+use crate::app::ExitStatus;
+use crate::error::{BlobResult, ErrorCategory};
+use crate::events::{self, SponsorBlockCut, SponsorBlockFile, SponsorBlockTotals};
+use crate::job::{EntryStatus, Job};
+use crate::parser::ReportFormat;
+use crate::tracklist::format_timestamp;
+use super::{interrupt, format_bytes, DownloadedFile};
+
+/// Everything that happened during a run, shown at the end and optionally exported with --report
+#[derive(Debug, Serialize)]
+pub(crate) struct RunSummary {
+    url: String,
+    job: String,
+    /// The user stopped the run with Ctrl-C
+    interrupted: bool,
+    succeeded: usize,
+    failed: usize,
+    /// Videos which were already in the download archive, or which an earlier run of the job downloaded
+    skipped: usize,
+    /// Size of the downloaded files
+    bytes: u64,
+    /// Measured in seconds
+    elapsed: f64,
+    /// Measured in bytes per second
+    average_speed: Option<f64>,
+    files: Vec<SummaryFile>,
+    /// The videos which couldn't be downloaded, grouped by the kind of error they ran into
+    errors: Vec<ErrorGroup>,
+    /// What yt-dlp printed as WARNING:, they didn't stop any download
+    warnings: Vec<String>,
+    /// How much of the downloaded files SponsorBlock removed or marked, None when it wasn't used
+    sponsorblock: Option<SponsorBlockTotals>,
+}
+
+#[derive(Debug, Serialize)]
+struct SummaryFile {
+    id: String,
+    title: Option<String>,
+    path: PathBuf,
+    size: Option<u64>,
+    /// Which format of the fallback chain the file was downloaded in, only known when a specific format was chosen
+    format: Option<UsedFormat>,
+}
+
+#[derive(Debug, Serialize)]
+struct UsedFormat {
+    /// Its position in the chain, 0 is the format the user chose
+    rung: usize,
+    description: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorGroup {
+    category: Option<ErrorCategory>,
+    reason: String,
+    /// What the user can do about it
+    hint: String,
+    videos: Vec<FailedVideo>,
+}
+
+#[derive(Debug, Serialize)]
+struct FailedVideo {
+    id: String,
+    title: Option<String>,
+    playlist_index: Option<usize>,
+    error: Option<String>,
+}
+
+impl RunSummary {
+    /// `downloaded` are the files downloaded by the run, failures are read from `job`
+    pub(crate) fn new(job: &Job, downloaded: &[DownloadedFile], skipped: usize, warnings: &[String], started: SystemTime) -> RunSummary {
+        let files: Vec<SummaryFile> = downloaded.iter()
+            .map(|file| SummaryFile {
+                id: file.id.clone(),
+                title: file.title.clone(),
+                path: file.filepath.clone(),
+                size: fs::metadata(&file.filepath).ok().map(|metadata| metadata.len()),
+                format: used_format(&job.state.download_config, file),
+            })
+            .collect();
+
+        let mut errors: Vec<ErrorGroup> = vec![];
+        for entry in job.state.entries.iter().filter(|entry| entry.status == EntryStatus::Failed) {
+            let video = FailedVideo {
+                id: entry.id.clone(),
+                title: entry.title.clone(),
+                playlist_index: entry.playlist_index,
+                error: entry.error.clone(),
+            };
+            match errors.iter_mut().find(|group| group.category == entry.category) {
+                Some(group) => group.videos.push(video),
+                None => {
+                    let category = entry.category.unwrap_or(ErrorCategory::Unknown);
+                    errors.push(ErrorGroup {
+                        category: entry.category,
+                        reason: category.to_string(),
+                        hint: category.hint().to_string(),
+                        videos: vec![video],
+                    })
+                }
+            }
+        }
+
+        // Retries often run into the same warnings again
+        let mut unique_warnings: Vec<String> = vec![];
+        for warning in warnings {
+            if !unique_warnings.contains(warning) {
+                unique_warnings.push(warning.clone());
+            }
+        }
+
+        let bytes = files.iter().filter_map(|file| file.size).sum();
+        let elapsed = started.elapsed().unwrap_or(Duration::ZERO).as_secs_f64();
+
+        RunSummary {
+            url: job.state.download_config.url.clone().unwrap_or_default(),
+            job: job.state.name.clone(),
+            interrupted: interrupt::interrupted(),
+            succeeded: files.len(),
+            failed: errors.iter().map(|group| group.videos.len()).sum(),
+            skipped,
+            bytes,
+            elapsed,
+            average_speed: if elapsed > 0.0 { Some(bytes as f64 / elapsed) } else { None },
+            sponsorblock: sponsorblock_totals(&job.state.download_config, downloaded),
+            files,
+            errors,
+            warnings: unique_warnings,
+        }
+    }
+
+    /// Failed videos make the run a partial or total failure, unless it was interrupted
+    pub(crate) fn exit_status(&self) -> ExitStatus {
+        if self.interrupted {
+            return ExitStatus::Interrupted;
+        }
+        let postprocessing_failed = self.errors.iter().any(|group| group.category == Some(ErrorCategory::Postprocessing));
+        if postprocessing_failed && which("ffmpeg").is_err() {
+            return ExitStatus::MissingDependency;
+        }
+        ExitStatus::from_counts(self.succeeded + self.skipped, self.failed)
+    }
+
+    /// The summary as it is emitted with --output-format json
+    pub(crate) fn to_event(&self) -> events::Event {
+        events::Event::RunSummary(events::RunSummary {
+            url: self.url.clone(),
+            job: self.job.clone(),
+            interrupted: self.interrupted,
+            succeeded: self.succeeded,
+            failed: self.failed,
+            skipped: self.skipped,
+            bytes: self.bytes,
+            elapsed: self.elapsed,
+            warnings: self.warnings.clone(),
+            exit_code: self.exit_status() as u8,
+            sponsorblock: self.sponsorblock.clone(),
+        })
+    }
+
+    pub(crate) fn print(&self) {
+        let title = if self.interrupted { "Summary (interrupted):" } else { "Summary:" };
+        println!("{}", title.bold().cyan());
+        println!("   {} downloaded, {} failed, {} skipped",
+                 self.succeeded.to_string().green(),
+                 self.failed.to_string().red(),
+                 self.skipped.to_string().yellow());
+        println!("   {} in {}{}",
+                 format_bytes(self.bytes),
+                 format_elapsed(self.elapsed),
+                 self.average_speed.map(|speed| format!(" ({}/s)", format_bytes(speed as u64))).unwrap_or_default());
+
+        for group in &self.errors {
+            println!("   {} ({}): {}", group.reason.red(), group.videos.len(), group.hint.italic());
+            for video in &group.videos {
+                println!("      {}: {}", video_name(video), video.error.as_deref().unwrap_or_default());
+            }
+        }
+
+        let fallbacks: Vec<&SummaryFile> = self.files.iter().filter(|file| file.format.as_ref().is_some_and(|format| format.rung > 0)).collect();
+        if !fallbacks.is_empty() {
+            println!("   {} ({}):", "Not available in the chosen format".yellow(), fallbacks.len());
+            for file in fallbacks {
+                println!("      {}: {}", file.title.as_deref().unwrap_or(&file.id), file.format.as_ref().map(|format| format.description.as_str()).unwrap_or_default());
+            }
+        }
+
+        if let (Some(sponsorblock), false) = (&self.sponsorblock, self.files.is_empty()) {
+            if sponsorblock.files.is_empty() {
+                println!("   SponsorBlock didn't find any segments to be {}", sponsorblock.action);
+            } else {
+                println!("   SponsorBlock {} {} in total:", sponsorblock.action, format_timestamp(sponsorblock.total.round() as u64));
+                for file in &sponsorblock.files {
+                    println!("      {}: {} ({})", file.title.as_deref().unwrap_or(&file.id), format_timestamp(file.total.round() as u64), cuts(file));
+                }
+            }
+        }
+
+        if !self.warnings.is_empty() {
+            println!("   {} ({}):", "Warnings".yellow(), self.warnings.len());
+            for warning in &self.warnings {
+                println!("      {}", warning);
+            }
+        }
+    }
+
+    /// Writes the summary to `path` for other programs to read, problems are reported without stopping blob-dl
+    pub(crate) fn write_report(&self, format: &ReportFormat, path: &Path) {
+        let result: BlobResult<()> = (|| {
+            let contents = match format {
+                ReportFormat::Json => serde_json::to_string_pretty(self)?,
+                ReportFormat::Markdown => self.to_markdown(),
+            };
+            fs::write(path, contents)?;
+            Ok(())
+        })();
+
+        match result {
+            Ok(()) => println!("{} Report saved to {}", "[blob-dl]".purple(), path.display()),
+            Err(err) => err.report(),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut markdown = format!("# blob-dl report{}\n\n- URL: {}\n- Job: {}\n- Downloaded: {}\n- Failed: {}\n- Skipped: {}\n- Size: {}\n- Elapsed: {}\n",
+                                   if self.interrupted { " (interrupted)" } else { "" },
+                                   self.url,
+                                   self.job,
+                                   self.succeeded,
+                                   self.failed,
+                                   self.skipped,
+                                   format_bytes(self.bytes),
+                                   format_elapsed(self.elapsed));
+        if let Some(speed) = self.average_speed {
+            markdown += &format!("- Average speed: {}/s\n", format_bytes(speed as u64));
+        }
+
+        if !self.files.is_empty() {
+            markdown += "\n## Downloaded files\n\n| Title | Id | Size | Format | Path |\n| --- | --- | --- | --- | --- |\n";
+            for file in &self.files {
+                markdown += &format!("| {} | {} | {} | {} | {} |\n",
+                                     escape_cell(file.title.as_deref().unwrap_or("")),
+                                     file.id,
+                                     file.size.map(format_bytes).unwrap_or_default(),
+                                     file.format.as_ref().map(|format| format.description.as_str()).unwrap_or_default(),
+                                     escape_cell(&file.path.to_string_lossy()));
+            }
+        }
+
+        if !self.errors.is_empty() {
+            markdown += "\n## Errors\n";
+            for group in &self.errors {
+                markdown += &format!("\n### {} ({})\n\n_{}_\n\n", group.reason, group.videos.len(), group.hint);
+                for video in &group.videos {
+                    markdown += &format!("- {}: {}\n", video_name(video), video.error.as_deref().unwrap_or_default());
+                }
+            }
+        }
+
+        if let Some(sponsorblock) = self.sponsorblock.as_ref().filter(|sponsorblock| !sponsorblock.files.is_empty()) {
+            markdown += &format!("\n## SponsorBlock\n\n{} {} in total\n\n| Title | Id | {} | Segments |\n| --- | --- | --- | --- |\n",
+                                 capitalize(&sponsorblock.action),
+                                 format_timestamp(sponsorblock.total.round() as u64),
+                                 capitalize(&sponsorblock.action));
+            for file in &sponsorblock.files {
+                markdown += &format!("| {} | {} | {} | {} |\n",
+                                     escape_cell(file.title.as_deref().unwrap_or("")),
+                                     file.id,
+                                     format_timestamp(file.total.round() as u64),
+                                     cuts(file));
+            }
+        }
+
+        if !self.warnings.is_empty() {
+            markdown += "\n## Warnings\n\n";
+            for warning in &self.warnings {
+                markdown += &format!("- {}\n", warning);
+            }
+        }
+        markdown
+    }
+}
+
+/// Finds which format of the fallback chain yt-dlp picked for `file`.
+///
+/// yt-dlp uses the first format of the chain which is available, so that is the first one the file matches
+fn used_format(download_config: &DownloadConfig, file: &DownloadedFile) -> Option<UsedFormat> {
+    let Some(VideoQualityAndFormatPreferences::UniqueFormat(id)) = &download_config.chosen_format else { return None };
+    let media_selected = download_config.media_selected.as_ref()?;
+    let format_id = file.format_id.as_deref()?;
+
+    download_config.format_chain(id).iter()
+        .enumerate()
+        .filter(|(_, fallback)| fallback.selector(media_selected).is_some())
+        .find(|(_, fallback)| fallback.matches(media_selected, format_id, file.height, file.vcodec.as_deref(), file.acodec.as_deref()))
+        .map(|(rung, fallback)| UsedFormat { rung, description: fallback.to_string() })
+}
+
+/// How much of every downloaded file SponsorBlock removed (or marked as chapters), broken down by category
+fn sponsorblock_totals(download_config: &DownloadConfig, downloaded: &[DownloadedFile]) -> Option<SponsorBlockTotals> {
+    let (categories, action) = match download_config.sponsorblock.as_ref()? {
+        SponsorBlockPreferences::Remove(categories) => (categories, "removed"),
+        SponsorBlockPreferences::Mark(categories) => (categories, "marked"),
+        SponsorBlockPreferences::Disabled => return None,
+    };
+
+    let files: Vec<SponsorBlockFile> = downloaded.iter()
+        .map(|file| {
+            let segments: Vec<SponsorBlockCut> = file.sponsorblock_chapters.iter()
+                .flatten()
+                .filter(|segment| categories.contains(&segment.category))
+                .map(|segment| SponsorBlockCut { category: segment.category.clone(), duration: segment.end_time - segment.start_time })
+                .collect();
+            SponsorBlockFile {
+                id: file.id.clone(),
+                title: file.title.clone(),
+                total: segments.iter().map(|segment| segment.duration).sum(),
+                segments,
+            }
+        })
+        .filter(|file| !file.segments.is_empty())
+        .collect();
+
+    Some(SponsorBlockTotals {
+        action: action.to_string(),
+        total: files.iter().map(|file| file.total).sum(),
+        files,
+    })
+}
+
+/// `sponsor 1:05, intro 0:10`
+fn cuts(file: &SponsorBlockFile) -> String {
+    file.segments.iter()
+        .map(|segment| format!("{} {}", segment.category, format_timestamp(segment.duration.round() as u64)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// Title, id and playlist index, whatever is known
+fn video_name(video: &FailedVideo) -> String {
+    let mut name = match &video.title {
+        Some(title) => format!("{} [{}]", title, video.id),
+        None => video.id.clone(),
+    };
+    if let Some(index) = video.playlist_index {
+        name = format!("#{} {}", index, name);
+    }
+    name
+}
+
+fn format_elapsed(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{:.1}s", seconds)
+    } else {
+        format_timestamp(seconds.round() as u64)
+    }
+}
+
+/// Pipes would end a markdown table cell
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembling::youtube::{FormatFallback, MediaSelection};
+    use crate::run::SponsorBlockSegment;
+
+    fn file(format_id: &str, height: u32, vcodec: &str) -> DownloadedFile {
+        DownloadedFile {
+            id: "dQw4w9WgXcQ".to_string(),
+            title: None,
+            filepath: PathBuf::from("video.mp4"),
+            duration: None,
+            playlist: None,
+            playlist_index: None,
+            description: None,
+            sponsorblock_chapters: None,
+            format_id: Some(format_id.to_string()),
+            height: Some(height),
+            vcodec: Some(vcodec.to_string()),
+            acodec: Some("mp4a.40.2".to_string()),
+        }
+    }
+
+    #[test]
+    fn used_formats() {
+        let mut download_config = DownloadConfig::empty();
+        download_config.media_selected = Some(MediaSelection::FullVideo);
+        download_config.chosen_format = Some(VideoQualityAndFormatPreferences::UniqueFormat("137".to_string()));
+        download_config.format_fallback = Some(vec![
+            FormatFallback::SameResolutionAndCodec { height: Some(1080), codec: "avc1".to_string() },
+            FormatFallback::AtMostHeight(1080),
+            FormatFallback::Best,
+        ]);
+        let used = |file: DownloadedFile| used_format(&download_config, &file).map(|used| (used.rung, used.description));
+
+        assert_eq!(used(file("137+140", 1080, "avc1.640028")), Some((0, "format 137".to_string())));
+        assert_eq!(used(file("299+140", 1080, "avc1.64002a")), Some((1, "1080p avc1".to_string())));
+        assert_eq!(used(file("22", 720, "avc1.64001F")), Some((2, "best up to 1080p".to_string())));
+        assert_eq!(used(file("308+140", 1440, "vp09.00.50.08")), Some((3, "best available".to_string())));
+
+        // Only UniqueFormat downloads have fallbacks
+        let mut best = download_config.clone();
+        best.chosen_format = Some(VideoQualityAndFormatPreferences::BestQuality);
+        assert!(used_format(&best, &file("137+140", 1080, "avc1.640028")).is_none());
+    }
+
+    #[test]
+    fn sponsorblock() {
+        let segment = |category: &str, start_time: f64, end_time: f64| SponsorBlockSegment { start_time, end_time, category: category.to_string() };
+        let mut sponsored = file("22", 720, "avc1.64001F");
+        sponsored.title = Some("Sponsored".to_string());
+        sponsored.sponsorblock_chapters = Some(vec![segment("sponsor", 10.0, 70.4), segment("outro", 100.0, 120.0), segment("intro", 0.0, 5.0)]);
+        let mut clean = file("22", 720, "avc1.64001F");
+        clean.id = "clean".to_string();
+        clean.sponsorblock_chapters = Some(vec![]);
+        let downloaded = [sponsored, clean];
+
+        let mut download_config = DownloadConfig::empty();
+        assert!(sponsorblock_totals(&download_config, &downloaded).is_none());
+        download_config.sponsorblock = Some(SponsorBlockPreferences::Disabled);
+        assert!(sponsorblock_totals(&download_config, &downloaded).is_none());
+
+        // outro wasn't chosen, files without segments are left out
+        download_config.sponsorblock = Some(SponsorBlockPreferences::Remove(vec!["sponsor".to_string(), "intro".to_string()]));
+        let totals = sponsorblock_totals(&download_config, &downloaded).unwrap();
+        assert_eq!(totals.action, "removed");
+        assert_eq!(totals.files.len(), 1);
+        assert_eq!(totals.files[0].title.as_deref(), Some("Sponsored"));
+        assert!((totals.total - 65.4).abs() < 1e-9);
+        assert_eq!(cuts(&totals.files[0]), "sponsor 1:00, intro 0:05");
+
+        download_config.sponsorblock = Some(SponsorBlockPreferences::Mark(vec!["outro".to_string()]));
+        let totals = sponsorblock_totals(&download_config, &downloaded).unwrap();
+        assert_eq!((totals.action.as_str(), totals.total), ("marked", 20.0));
+    }
+}
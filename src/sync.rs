@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::analyzer::DownloadOption;
+use crate::app::ExitStatus;
+use crate::assembling;
+use crate::assembling::youtube::{self, config::DownloadConfig};
+use crate::error::BlobResult;
+use crate::m3u;
+use crate::job::Job;
+use crate::parser::{RemovedEntries, ReportPreference, Verbosity};
+use crate::run::{self, DownloadedFile};
+
+/// Every synced directory remembers which video each of its files comes from here
+const STATE_FILE_NAME: &str = ".blob-dl-sync.json";
+
+/// Entries which aren't in the playlist anymore are moved here (inside the synced directory)
+const REMOVED_DIR_NAME: &str = "removed";
+
+/// What blob-dl knows about a synced directory
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncState {
+    url: String,
+    /// The preferences used the first time, so that later syncs don't ask again
+    download_config: DownloadConfig,
+    entries: Vec<SyncedEntry>,
+}
+
+/// A file in the synced directory and the video it was downloaded from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncedEntry {
+    id: String,
+    title: Option<String>,
+    playlist_index: usize,
+    /// Relative to the synced directory
+    file: String,
+    /// Measured in seconds
+    duration: Option<f64>,
+}
+
+/// Something sync has to do to make the directory match the playlist
+#[derive(Debug)]
+enum SyncAction {
+    Download { index: usize, title: String },
+    Rename { from: String, to: String },
+    /// The video isn't in the playlist anymore
+    Remove { file: String },
+}
+
+/// Options passed to `blob-dl sync`
+pub(crate) struct SyncOptions<'a> {
+    pub(crate) url: &'a str,
+    pub(crate) directory: &'a Path,
+    pub(crate) dry_run: bool,
+    pub(crate) removed: &'a RemovedEntries,
+    pub(crate) jobs: Option<usize>,
+    pub(crate) verbosity: &'a Verbosity,
+    pub(crate) report: Option<&'a ReportPreference>,
+}
+
+/// `blob-dl sync <url> <dir>`: makes `dir` a copy of the playlist as it is now.
+///
+/// New entries are downloaded, files are renamed when their index changes and entries which were removed from
+/// the playlist are kept, moved to `removed/` or deleted. `user_config` is only used the first time
+/// a directory is synced (or if a config file was passed explicitly), after that the preferences are read from the state file.
+///
+/// The preferences are only asked for once the plan has been shown, `--dry-run` never asks anything.
+/// The exit status depends on how many of the new entries could be downloaded
+pub(crate) fn sync(options: &SyncOptions, user_config: Option<DownloadConfig>) -> BlobResult<ExitStatus> {
+    let state_path = options.directory.join(STATE_FILE_NAME);
+    let previous = read_state(&state_path)?;
+
+    let mut entries = previous.as_ref().map(|state| state.entries.clone()).unwrap_or_default();
+
+    // Files which were deleted by hand are downloaded again
+    entries.retain(|entry| options.directory.join(&entry.file).exists());
+
+    let items = youtube::get_playlist_items(options.url, true)?;
+    if items.is_empty() {
+        // Without this check a network problem would look like an empty playlist and every file would be removed
+        println!("{} The playlist's entries couldn't be listed, nothing was changed", "[blob-dl]".purple());
+        return Ok(ExitStatus::Failure);
+    }
+    let last_index = items.iter().map(|item| item.playlist_index).max().unwrap_or(0);
+    let plan = make_plan(&items, &entries, last_index);
+
+    if plan.is_empty() {
+        println!("{} {} is already in sync", "[blob-dl]".purple(), options.directory.display());
+        return Ok(ExitStatus::Success);
+    }
+    print_plan(&plan, options.removed);
+    // The plan only depends on the playlist and the directory, so nothing has to be asked
+    if options.dry_run {
+        return Ok(ExitStatus::Success);
+    }
+
+    let mut download_config = match (user_config, previous) {
+        (Some(user_config), _) => resolve_preferences(options, user_config)?,
+        (None, Some(state)) => state.download_config,
+        (None, None) => resolve_preferences(options, DownloadConfig::empty())?,
+    };
+    // The directory could have been moved, or synced from somewhere else
+    download_config.url = Some(options.url.to_string());
+    download_config.output_path = Some(options.directory.to_string_lossy().to_string());
+
+    fs::create_dir_all(options.directory)?;
+    apply_removals(&plan, &mut entries, options)?;
+    apply_renames(&plan, &mut entries, &items, options.directory)?;
+
+    // Download the new entries
+    let mut status = ExitStatus::Success;
+    let mut commands = vec![];
+    let mut new_items = vec![];
+    for action in &plan {
+        if let SyncAction::Download { index, .. } = action {
+            commands.push((download_config.build_command_for_sync(*index, last_index)?, *index));
+            new_items.extend(items.iter().filter(|item| item.playlist_index == *index).cloned());
+        }
+    }
+    if !commands.is_empty() {
+        let jobs = options.jobs.or(download_config.advanced.parallel_downloads).unwrap_or(1);
+        let skipped = items.len() - commands.len();
+        // Like every other download, the run is kept track of by a job: its summary lists the entries which failed
+        // (errors are matched to them by index) and it ends up in the history
+        let mut job = Job::create(Some(&job_name(options.directory)), download_config.clone(), true)?;
+        job.add_entries(&new_items)?;
+        let (downloaded, run_status) = run::run_playlist_entries(commands, last_index, skipped, jobs, &mut job, options.verbosity, options.report)?;
+        status = run_status;
+
+        for file in downloaded {
+            entries.push(synced_entry(&file, options.directory, &items));
+        }
+        if !job.failed_entries().is_empty() {
+            println!("{} Failed entries will be downloaded by the next sync", "[blob-dl]".purple());
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.playlist_index);
+    let state = SyncState { url: options.url.to_string(), download_config, entries };
+    fs::write(&state_path, serde_json::to_string_pretty(&state)?)?;
+
+    m3u::write_playlists(&state.entries.iter().map(|entry| DownloadedFile {
+        id: entry.id.clone(),
+        title: entry.title.clone(),
+        filepath: options.directory.join(&entry.file),
+        duration: entry.duration,
+        playlist: None,
+        playlist_index: Some(entry.playlist_index),
+        description: None,
+        sponsorblock_chapters: None,
+        format_id: None,
+        height: None,
+        vcodec: None,
+        acodec: None,
+    }).collect::<Vec<_>>());
+
+    println!("{} {} is in sync with the playlist", "[blob-dl]".purple(), options.directory.display());
+    Ok(status)
+}
+
+/// Asks the user for the preferences which aren't in `user_config`.
+///
+/// The output directory and index prefixes are decided by sync
+fn resolve_preferences(options: &SyncOptions, mut user_config: DownloadConfig) -> BlobResult<DownloadConfig> {
+    user_config.output_path = Some(options.directory.to_string_lossy().to_string());
+    user_config.include_indexes = Some(true);
+    // Every video has to end up in exactly one file
+    user_config.split_by_tracklist = Some(false);
+    user_config.sections = None;
+
+    let (_, download_config) = assembling::generate_command(options.url, &DownloadOption::YtPlaylist, user_config)?;
+    Ok(download_config)
+}
+
+/// Syncs of the same directory share a job, so that `blob-dl retry-failed` can be used on the last one
+fn job_name(directory: &Path) -> String {
+    let directory = fs::canonicalize(directory).unwrap_or_else(|_| directory.to_path_buf());
+    let name: String = directory.to_string_lossy().chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .take(80)
+        .collect();
+    format!("sync-{}", name)
+}
+
+fn read_state(path: &Path) -> BlobResult<Option<SyncState>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Compares what is in the directory with what is in the playlist
+fn make_plan(items: &[youtube::PlaylistItem], entries: &[SyncedEntry], last_index: usize) -> Vec<SyncAction> {
+    let synced: HashMap<&str, &SyncedEntry> = entries.iter().map(|entry| (entry.id.as_str(), entry)).collect();
+    let width = last_index.to_string().len();
+    let mut plan = vec![];
+
+    for entry in entries {
+        if !items.iter().any(|item| item.id == entry.id) {
+            plan.push(SyncAction::Remove { file: entry.file.clone() });
+        }
+    }
+
+    for item in items {
+        match synced.get(item.id.as_str()) {
+            Some(entry) => {
+                let renamed = with_index(&entry.file, item.playlist_index, width);
+                if renamed != entry.file {
+                    plan.push(SyncAction::Rename { from: entry.file.clone(), to: renamed });
+                }
+            }
+            None => plan.push(SyncAction::Download {
+                index: item.playlist_index,
+                title: item.title.clone().unwrap_or_else(|| item.id.clone()),
+            }),
+        }
+    }
+
+    plan
+}
+
+/// Replaces the `12_` prefix of a file name with the new index, padded like yt-dlp does
+fn with_index(file: &str, index: usize, width: usize) -> String {
+    let name = match file.split_once('_') {
+        Some((prefix, rest)) if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => file,
+    };
+    format!("{:0width$}_{}", index, name, width = width)
+}
+
+fn print_plan(plan: &[SyncAction], removed: &RemovedEntries) {
+    println!("{}", "Changes needed to sync the directory:".bold().cyan());
+
+    for action in plan {
+        match action {
+            SyncAction::Download { index, title } => println!("   {} #{} {}", "download".green(), index, title),
+            SyncAction::Rename { from, to } => println!("   {} {} -> {}", "rename".cyan(), from, to),
+            SyncAction::Remove { file } => match removed {
+                RemovedEntries::Keep => println!("   {} {} (not in the playlist anymore)", "keep".yellow(), file),
+                RemovedEntries::Move => println!("   {} {} to {}/", "move".yellow(), file, REMOVED_DIR_NAME),
+                RemovedEntries::Delete => println!("   {} {}", "delete".red(), file),
+            },
+        }
+    }
+}
+
+/// Entries which left the playlist stop being tracked, their files are moved or deleted if the user asked for it
+fn apply_removals(plan: &[SyncAction], entries: &mut Vec<SyncedEntry>, options: &SyncOptions) -> BlobResult<()> {
+    for action in plan {
+        if let SyncAction::Remove { file } = action {
+            let path = options.directory.join(file);
+            match options.removed {
+                RemovedEntries::Keep => {}
+                RemovedEntries::Move => {
+                    let removed_dir = options.directory.join(REMOVED_DIR_NAME);
+                    fs::create_dir_all(&removed_dir)?;
+                    fs::rename(&path, removed_dir.join(file))?;
+                }
+                RemovedEntries::Delete => fs::remove_file(&path)?,
+            }
+            entries.retain(|entry| entry.file != *file);
+        }
+    }
+    Ok(())
+}
+
+/// Files are first given temporary names, otherwise swapping two entries would overwrite one of them
+fn apply_renames(plan: &[SyncAction], entries: &mut [SyncedEntry], items: &[youtube::PlaylistItem], directory: &Path) -> BlobResult<()> {
+    let renames: Vec<(&String, &String)> = plan.iter()
+        .filter_map(|action| match action {
+            SyncAction::Rename { from, to } => Some((from, to)),
+            _ => None,
+        })
+        .collect();
+
+    let temporary = |i: usize| directory.join(format!(".blob-dl-sync-{}.tmp", i));
+    for (i, (from, _)) in renames.iter().enumerate() {
+        fs::rename(directory.join(from), temporary(i))?;
+    }
+    for (i, (from, to)) in renames.iter().enumerate() {
+        fs::rename(temporary(i), directory.join(to))?;
+
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.file == **from) {
+            entry.file = to.to_string();
+            if let Some(item) = items.iter().find(|item| item.id == entry.id) {
+                entry.playlist_index = item.playlist_index;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn synced_entry(file: &DownloadedFile, directory: &Path, items: &[youtube::PlaylistItem]) -> SyncedEntry {
+    let relative: PathBuf = file.filepath.strip_prefix(directory)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|_| file.filepath.file_name().map(PathBuf::from).unwrap_or_default());
+
+    SyncedEntry {
+        id: file.id.clone(),
+        title: file.title.clone(),
+        playlist_index: file.playlist_index
+            .or_else(|| items.iter().find(|item| item.id == file.id).map(|item| item.playlist_index))
+            .unwrap_or(0),
+        file: relative.to_string_lossy().to_string(),
+        duration: file.duration,
+    }
+}
@@ -0,0 +1,287 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use colored::Colorize;
+
+use crate::error::{BlobResult, BlobdlError};
+use crate::run::DownloadedFile;
+
+/// A virtual chapter built from a line like `03:41 Song B` found in a video's description
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Track {
+    /// Seconds from the beginning of the file
+    pub(crate) start: u64,
+    /// Where the next track begins, None for the last track (it lasts until the end of the file)
+    pub(crate) end: Option<u64>,
+    pub(crate) title: String,
+}
+
+/// Parses a `mm:ss` or `h:mm:ss` timestamp into seconds
+pub(crate) fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let sections: Vec<&str> = timestamp.split(':').collect();
+
+    // Every section has to be a plain number
+    if sections.iter().any(|s| s.is_empty() || !s.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+
+    match sections[..] {
+        [minutes, seconds] => {
+            let seconds = parse_sexagesimal(seconds)?;
+            Some(minutes.parse::<u64>().ok()? * 60 + seconds)
+        }
+        [hours, minutes, seconds] => {
+            let minutes = parse_sexagesimal(minutes)?;
+            let seconds = parse_sexagesimal(seconds)?;
+            Some(hours.parse::<u64>().ok()? * 3600 + minutes * 60 + seconds)
+        }
+        _ => None,
+    }
+}
+
+/// Formats a number of seconds as a `m:ss` or `h:mm:ss` timestamp
+pub(crate) fn format_timestamp(seconds: u64) -> String {
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, (seconds % 3600) / 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Minutes and seconds in a timestamp are always written with 2 digits and can't go past 59
+fn parse_sexagesimal(section: &str) -> Option<u64> {
+    if section.len() != 2 {
+        return None;
+    }
+    section.parse().ok().filter(|value| *value < 60)
+}
+
+/// Turns the tracklist found in a video's description into a list of tracks.
+///
+/// Lines without a valid timestamp are ignored, as well as timestamps that don't come after the previous one
+/// (descriptions often mention moments of the video which aren't part of the tracklist).
+/// If `duration` is known, timestamps past the end of the video are ignored too.
+///
+/// Returns an empty Vec if the description doesn't contain at least 2 tracks
+pub(crate) fn parse_description(description: &str, duration: Option<f64>) -> Vec<Track> {
+    let mut tracks: Vec<Track> = vec![];
+
+    for (start, title) in description.lines().filter_map(parse_line) {
+        if let Some(duration) = duration {
+            if start as f64 >= duration {
+                continue;
+            }
+        }
+        if let Some(previous) = tracks.last() {
+            if start <= previous.start {
+                continue;
+            }
+        }
+        tracks.push(Track { start, end: None, title });
+    }
+
+    if tracks.len() < 2 {
+        return vec![];
+    }
+
+    // Every track ends where the next one begins
+    let starts: Vec<u64> = tracks.iter().skip(1).map(|track| track.start).collect();
+    for (track, next_start) in tracks.iter_mut().zip(starts) {
+        track.end = Some(next_start);
+    }
+
+    tracks
+}
+
+/// Extracts the timestamp and the title from a single description line.
+///
+/// The timestamp can be at the beginning (`00:00 Song A`, `1. [00:00] - Song A`, `00:00 - 03:41 Song A`)
+/// or at the end of the line (`Song A (03:41)`)
+fn parse_line(line: &str) -> Option<(u64, String)> {
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+
+    // Drop a leading track number ("1.", "01)", "#1")
+    if tokens.len() > 2 && is_track_number(tokens[0]) {
+        tokens.remove(0);
+    }
+
+    if let Some(start) = tokens.first().and_then(|token| parse_timestamp(strip_decorations(token))) {
+        let mut rest = &tokens[1..];
+
+        // The line describes a range: only the start is needed
+        if rest.len() > 1 && is_separator(rest[0]) && parse_timestamp(strip_decorations(rest[1])).is_some() {
+            rest = &rest[2..];
+        } else if !rest.is_empty() && parse_timestamp(strip_decorations(rest[0])).is_some() {
+            rest = &rest[1..];
+        }
+
+        return clean_title(rest).map(|title| (start, title));
+    }
+
+    if let Some(start) = tokens.last().and_then(|token| parse_timestamp(strip_decorations(token))) {
+        return clean_title(&tokens[..tokens.len() - 1]).map(|title| (start, title));
+    }
+
+    None
+}
+
+/// Removes the brackets timestamps are often wrapped in
+fn strip_decorations(token: &str) -> &str {
+    token.trim_matches(|c| "[](){},".contains(c)).trim_end_matches(':')
+}
+
+fn is_track_number(token: &str) -> bool {
+    let number = token.trim_matches(|c| ".)#".contains(c));
+    !number.is_empty() && number.len() <= 3 && number.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_separator(token: &str) -> bool {
+    matches!(token, "-" | "–" | "—" | "~" | "to")
+}
+
+/// Joins the words of a title and strips the separators around it, returns None if nothing is left
+fn clean_title(words: &[&str]) -> Option<String> {
+    let title = words.join(" ");
+    let title = title.trim_matches(|c: char| c.is_whitespace() || "-–—|:.~•".contains(c));
+
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+/// Splits every downloaded file whose description contains a tracklist
+///
+/// Problems with a single file are reported without stopping the others from being split
+pub(crate) fn split_downloads(downloaded: &[DownloadedFile]) {
+    for file in downloaded {
+        let tracks = parse_description(file.description.as_deref().unwrap_or(""), file.duration);
+
+        if tracks.is_empty() {
+            println!("{} No tracklist was found in the description of {}", "[blob-dl]".purple(), file.filepath.display());
+            continue;
+        }
+
+        let album = file.title.clone().unwrap_or_else(|| file.id.clone());
+        match split_audio(&file.filepath, &album, &tracks) {
+            Ok(files) => println!("{} Split {} into {} tracks", "[blob-dl]".purple(), file.filepath.display(), files.len()),
+            Err(err) => err.report(),
+        }
+    }
+}
+
+/// Uses ffmpeg to cut `path` into one file per track, without re-encoding.
+///
+/// The tracks are saved in a directory named after the original file, which is kept,
+/// and are tagged with their title, their track number and the album they come from
+pub(crate) fn split_audio(path: &Path, album: &str, tracks: &[Track]) -> BlobResult<Vec<PathBuf>> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("mka");
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("tracks");
+
+    let directory = path.with_file_name(stem);
+    std::fs::create_dir_all(&directory)?;
+
+    let mut created = vec![];
+
+    for (i, track) in tracks.iter().enumerate() {
+        let output = directory.join(format!("{:02} - {}.{}", i + 1, sanitize_filename(&track.title), extension));
+
+        let mut command = Command::new("ffmpeg");
+        command.args(["-hide_banner", "-loglevel", "error", "-nostdin", "-y"]);
+        command.arg("-i").arg(path);
+        command.arg("-ss").arg(track.start.to_string());
+        if let Some(end) = track.end {
+            command.arg("-to").arg(end.to_string());
+        }
+        // Only keep the audio and don't re-encode it
+        command.args(["-map", "0:a", "-c", "copy"]);
+        command.arg("-metadata").arg(format!("title={}", track.title));
+        command.arg("-metadata").arg(format!("track={}/{}", i + 1, tracks.len()));
+        command.arg("-metadata").arg(format!("album={}", album));
+        command.arg(&output);
+
+        let status = command.stdout(Stdio::null()).status()?;
+        if !status.success() {
+            return Err(BlobdlError::TracklistSplitError);
+        }
+        created.push(output);
+    }
+
+    Ok(created)
+}
+
+/// Replaces the characters that aren't allowed in filenames on some operating systems
+fn sanitize_filename(title: &str) -> String {
+    title.chars()
+        .map(|c| if "/\\:*?\"<>|".contains(c) || c.is_control() { '_' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("00:00"), Some(0));
+        assert_eq!(parse_timestamp("3:41"), Some(221));
+        assert_eq!(parse_timestamp("63:05"), Some(3785));
+        assert_eq!(parse_timestamp("1:02:03"), Some(3723));
+        assert_eq!(parse_timestamp("1:2:03"), None);
+        assert_eq!(parse_timestamp("03:60"), None);
+        assert_eq!(parse_timestamp("1:60:00"), None);
+        assert_eq!(parse_timestamp("3:4"), None);
+        assert_eq!(parse_timestamp("12"), None);
+        assert_eq!(parse_timestamp("a:00"), None);
+        assert_eq!(parse_timestamp("1:00:00:00"), None);
+
+        assert_eq!(format_timestamp(221), "3:41");
+        assert_eq!(format_timestamp(3723), "1:02:03");
+    }
+
+    #[test]
+    fn simple_tracklist() {
+        let description = "Full album, enjoy!\n\nTracklist:\n00:00 Song A\n03:41 Song B\n07:02 Song C\n\nFollow me on socials";
+
+        assert_eq!(parse_description(description, None), vec![
+            Track { start: 0, end: Some(221), title: "Song A".to_string() },
+            Track { start: 221, end: Some(422), title: "Song B".to_string() },
+            Track { start: 422, end: None, title: "Song C".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn decorated_lines() {
+        let description = "1. [00:00] - Intro\n2. (4:10) Song B\n0:08:00 - 0:12:30 | Song C\nSong D 1:02:03\n";
+
+        let tracks = parse_description(description, None);
+        let titles: Vec<&str> = tracks.iter().map(|t| t.title.as_str()).collect();
+        let starts: Vec<u64> = tracks.iter().map(|t| t.start).collect();
+
+        assert_eq!(titles, vec!["Intro", "Song B", "Song C", "Song D"]);
+        assert_eq!(starts, vec![0, 250, 480, 3723]);
+    }
+
+    #[test]
+    fn malformed_lines_are_ignored() {
+        let description = "00:00 Song A\n3:4 Broken\n02:99 Broken\n05:00\n04:00 Song B\n03:00 Out of order\n1:00:00 After the end";
+
+        let tracks = parse_description(description, Some(600.0));
+
+        assert_eq!(tracks, vec![
+            Track { start: 0, end: Some(240), title: "Song A".to_string() },
+            Track { start: 240, end: None, title: "Song B".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn single_timestamp_is_not_a_tracklist() {
+        assert!(parse_description("The drop is at 02:30, enjoy", None).is_empty());
+        assert!(parse_description("", None).is_empty());
+    }
+
+    #[test]
+    fn filenames_are_sanitized() {
+        assert_eq!(sanitize_filename("AC/DC: Back in Black?"), "AC_DC_ Back in Black_");
+    }
+}
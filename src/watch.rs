@@ -0,0 +1,119 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use colored::Colorize;
+
+use crate::analyzer;
+use crate::app::ExitStatus;
+use crate::assembling;
+use crate::assembling::youtube::config::{ArchivePreference, DownloadConfig};
+use crate::error::BlobResult;
+use crate::history;
+use crate::job::Job;
+use crate::parser::{ReportPreference, Verbosity};
+use crate::run;
+
+/// Options passed to `blob-dl watch`
+pub(crate) struct WatchOptions<'a> {
+    pub(crate) urls: &'a [String],
+    /// Seconds between the start of a cycle and the start of the next one
+    pub(crate) interval: u64,
+    /// Only check the targets once, for cron and the like
+    pub(crate) once: bool,
+    pub(crate) jobs: Option<usize>,
+    pub(crate) verbosity: &'a Verbosity,
+    /// Every check overwrites the report, so it is about the last target which was checked
+    pub(crate) report: Option<&'a ReportPreference>,
+}
+
+/// A channel or playlist which is being watched and the preferences its new videos are downloaded with
+struct Target {
+    url: String,
+    download_config: DownloadConfig,
+}
+
+/// `blob-dl watch <url>...`: checks the targets every `interval` seconds and downloads the videos which aren't in
+/// the download archive yet.
+///
+/// Preferences are chosen once for every target (from `user_config` when there is one), then nobody is asked anything:
+/// failed videos are retried automatically and, since they don't end up in the archive, checked again by the next cycle.
+///
+/// With `once` the exit status is the one of the only cycle, otherwise watch only stops when it is interrupted
+pub(crate) fn watch(options: &WatchOptions, user_config: Option<DownloadConfig>) -> BlobResult<ExitStatus> {
+    let mut targets = vec![];
+    for url in options.urls {
+        let mut user_config = user_config.clone().unwrap_or_else(DownloadConfig::empty);
+        if options.jobs.is_some() {
+            user_config.advanced.parallel_downloads = options.jobs;
+        }
+        let download_option = analyzer::analyze_url(url)?;
+        let (_, mut download_config) = assembling::generate_command(url, &download_option, user_config)?;
+
+        // The archive is how watch knows what is new
+        if download_config.advanced.download_archive.is_none() {
+            download_config.advanced.download_archive = Some(ArchivePreference::Global);
+        }
+        if download_config.advanced.retry.is_none() {
+            download_config.advanced.retry = Some(Default::default());
+        }
+        targets.push(Target { url: url.clone(), download_config });
+    }
+
+    let mut cycle = 1;
+    loop {
+        let started = SystemTime::now();
+        println!("{} Watch cycle {} started on {} (UTC)",
+                 "[blob-dl]".purple(), cycle, history::format_date(unix_time(started)));
+
+        let mut new_videos = 0;
+        let mut statuses = vec![];
+        for target in &targets {
+            println!("{} Checking {}", "[blob-dl]".purple(), target.url);
+            match check(target, options) {
+                Ok((downloaded, status)) => {
+                    new_videos += downloaded;
+                    statuses.push(status);
+                }
+                // One broken target doesn't stop the others from being watched
+                Err(err) => {
+                    err.report();
+                    statuses.push(err.exit_status());
+                }
+            }
+            if run::interrupted() {
+                return Ok(ExitStatus::Interrupted);
+            }
+        }
+        println!("{} Watch cycle {} is over, {} new video(s) downloaded", "[blob-dl]".purple(), cycle, new_videos);
+
+        if options.once {
+            return Ok(statuses.into_iter().reduce(ExitStatus::combine).unwrap_or(ExitStatus::Success));
+        }
+        let next = started + Duration::from_secs(options.interval);
+        println!("{} Next check on {} (UTC)", "[blob-dl]".purple(), history::format_date(unix_time(next)));
+        std::thread::sleep(next.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO));
+        cycle += 1;
+    }
+}
+
+/// Downloads what is new in a target, returns how many videos were downloaded and how the run ended
+fn check(target: &Target, options: &WatchOptions) -> BlobResult<(usize, ExitStatus)> {
+    // Every cycle starts a fresh job, so `blob-dl retry-failed` can still be used on the last one
+    let mut job = Job::create(Some(&job_name(&target.url)), target.download_config.clone(), true)?;
+    let command = job.state.download_config.build_command()?.0;
+    let status = run::run_and_observe(command, &mut job, options.verbosity, options.report)?;
+
+    Ok((job.state.entries.iter().filter(|entry| entry.file.is_some()).count(), status))
+}
+
+/// Jobs of watched targets are named after their url, so every target always has the same job
+fn job_name(url: &str) -> String {
+    let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let name: String = url.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .take(80)
+        .collect();
+    format!("watch-{}", name)
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
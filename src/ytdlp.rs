@@ -0,0 +1,20 @@
+use std::ffi::OsString;
+use std::process::Command;
+use which::which;
+
+/// Makes blob-dl run another yt-dlp executable instead of the one in PATH, such as a stub in tests
+pub(crate) const YTDLP_ENV_VAR: &str = "BLOBDL_YTDLP";
+
+/// The yt-dlp executable blob-dl runs
+fn program() -> OsString {
+    std::env::var_os(YTDLP_ENV_VAR).unwrap_or_else(|| OsString::from("yt-dlp"))
+}
+
+/// Every yt-dlp process blob-dl starts is built from this
+pub(crate) fn command() -> Command {
+    Command::new(program())
+}
+
+pub(crate) fn is_installed() -> bool {
+    which(program()).is_ok()
+}
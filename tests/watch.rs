@@ -0,0 +1,111 @@
+// `blob-dl watch` against a stub yt-dlp, which is a shell script
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Lists a playlist of 3 videos, downloads the ones which aren't in the archive and logs every video it downloads
+const STUB: &str = r#"#!/bin/sh
+[ "$1" = "--version" ] && echo 2025.03.31 && exit 0
+dir=$(dirname "$0")
+log=""; archive=""; flat=""
+while [ $# -gt 0 ]; do
+  case "$1" in
+    --flat-playlist) flat=1 ;;
+    --download-archive) archive="$2"; shift ;;
+    --print-to-file) log="$3"; shift 2 ;;
+  esac
+  shift
+done
+if [ -n "$flat" ]; then
+  for n in 1 2 3; do echo "{\"id\": \"vid$n\", \"title\": \"Title $n\", \"playlist_index\": $n}"; done
+  exit 0
+fi
+for n in 1 2 3; do
+  if [ -n "$archive" ] && grep -q "youtube vid$n" "$archive" 2>/dev/null; then
+    echo "[download] vid$n has already been recorded in the archive"
+    continue
+  fi
+  echo "[download] Downloading item $n of 3"
+  echo "[youtube] vid$n: Downloading webpage"
+  touch "$dir/out/Title $n.mp3"
+  echo "vid$n" >> "$dir/downloaded"
+  echo "{\"id\": \"vid$n\", \"title\": \"Title $n\", \"filepath\": \"$dir/out/Title $n.mp3\", \"playlist_index\": $n}" >> "$log"
+  echo "youtube vid$n" >> "$archive"
+done
+"#;
+
+#[test]
+fn watch_once_downloads_what_is_missing_from_the_archive() {
+    let (dir, output) = watch_once("playlist", "https://www.youtube.com/playlist?list=PLwatchtest");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}\n{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert_eq!(read(&dir.join("downloaded")), "vid2\n");
+    assert_eq!(read(&dir.join("archive.txt")), "youtube vid1\nyoutube vid3\nyoutube vid2\n");
+    assert!(stdout.contains("1 new, 2 already downloaded"), "{}", stdout);
+    assert!(stdout.contains("Watch cycle 1 started"), "{}", stdout);
+    assert!(stdout.contains("Watch cycle 1 is over, 1 new video(s) downloaded"), "{}", stdout);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn watch_once_accepts_channels() {
+    let (dir, output) = watch_once("channel", "https://www.youtube.com/@Handle/videos");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}\n{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert_eq!(read(&dir.join("downloaded")), "vid2\n");
+    assert!(stdout.contains("Watch cycle 1 is over, 1 new video(s) downloaded"), "{}", stdout);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// Runs `blob-dl watch --once url` against the stub, with vid1 and vid3 already in the archive.
+///
+/// Returns the directory everything is kept in and what blob-dl did
+fn watch_once(name: &str, url: &str) -> (PathBuf, Output) {
+    let dir = std::env::temp_dir().join(format!("blob-dl-watch-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("out")).unwrap();
+
+    let stub = dir.join("yt-dlp");
+    fs::write(&stub, STUB).unwrap();
+    fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let archive = dir.join("archive.txt");
+    fs::write(&archive, "youtube vid1\nyoutube vid3\n").unwrap();
+
+    let config = dir.join("config.json");
+    fs::write(&config, serde_json::json!({
+        "output_path": dir.join("out"),
+        "include_indexes": false,
+        "chosen_format": "BestQuality",
+        "media_selected": "AudioOnly",
+        "download_target": "YtPlaylist",
+        "split_by_tracklist": false,
+        "sponsorblock": "Disabled",
+        "download_archive": { "Custom": archive },
+    }).to_string()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_blob-dl"))
+        .args(["watch", "--once", "-l"])
+        .arg(&config)
+        .arg(url)
+        .env("BLOBDL_YTDLP", &stub)
+        // Jobs and the history are saved in here
+        .env("HOME", &dir)
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .output()
+        .unwrap();
+
+    (dir, output)
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}